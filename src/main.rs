@@ -1,9 +1,9 @@
 extern crate sxd_document;
 extern crate sxd_xpath;
 extern crate dirs;
-extern crate ureq;
-extern crate terminal_size;
-extern crate crossterm_input;
+extern crate tokio;
+extern crate reqwest;
+extern crate futures;
 extern crate crossterm;
 extern crate serde;
 
@@ -15,17 +15,27 @@ use std::fs;
 use std::env;
 use std::io;
 use std::path::Path;
-use std::io::{Read, Write};
+use std::io::{BufRead, Read, Write};
 use std::io::Error;
 use std::io::ErrorKind::NotFound;
 use sxd_document::dom::Element;
-use terminal_size::{Width, Height, terminal_size};
 use std::cmp::min;
 use std::process::{Command, Stdio};
-use crossterm_input::{input, RawScreen, InputEvent};
-use crossterm_input::KeyEvent::{Char, Down, Up, Left, Right};
-use rayon::prelude::*;
-use webbrowser;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::hash_map::RandomState;
+use std::cell::{Cell, RefCell};
+use std::hash::BuildHasher;
+use std::time::{Duration, Instant, SystemTime};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::os::unix::net::UnixStream;
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use crossterm::{cursor, execute, queue, terminal};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use Key::{Char, Down, Up, Left, Right};
+use futures::stream::{self, StreamExt};
+use std::future::Future;
 
 fn default_mpv_mode() -> bool {
     true
@@ -35,18 +45,212 @@ fn default_mpv_path() -> String {
     "/usr/bin/mpv".to_string()
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+fn default_watch_later_path() -> String {
+    "/tmp/yts_watch_later".to_string()
+}
+
+fn default_batch_file_path() -> String {
+    "/tmp/yts_batch.txt".to_string()
+}
+
+fn default_theme() -> String {
+    "default".to_string()
+}
+
+fn default_request_timeout_seconds() -> u64 {
+    30
+}
+
+fn default_request_retries() -> u32 {
+    2
+}
+
+fn default_retry_backoff_ms() -> u64 {
+    1000
+}
+
+fn default_date_format() -> String {
+    "%m-%d".to_string()
+}
+
+fn default_sort_order() -> String {
+    "date".to_string()
+}
+
+fn default_thumbnails() -> String {
+    "auto".to_string()
+}
+
+fn default_retention_days() -> u64 {
+    90
+}
+
+fn default_notify_command() -> Vec<String> {
+    vec!["notify-send".to_string()]
+}
+
+fn default_state_path() -> String {
+    "/tmp/yts_state.json".to_string()
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+struct ChannelOverride {
+    #[serde(default)]
+    youtubedl_format: Option<String>,
+    #[serde(default)]
+    video_extension: Option<String>,
+    #[serde(default)]
+    players: Option<Vec<Vec<String>>>,
+    #[serde(default)]
+    extra_args: Vec<String>,
+}
+
+/// kid-facing profile: only allowed_channels are shown, changes require the pin
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct RestrictedProfile {
+    allowed_channels: Vec<String>,
+    pin: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 struct AppConfig {
     video_path: String,
     cache_path: String,
+    #[serde(default = "default_state_path")]
+    state_path: String,
     youtubedl_format: String,
     video_extension: String,
     players: Vec<Vec<String>>,
     channel_ids: Vec<String>,
+    #[serde(default)]
+    playlist_ids: Vec<String>,
+    #[serde(default)]
+    peertube_feeds: Vec<String>,
+    #[serde(default)]
+    odysee_channels: Vec<String>,
+    #[serde(default)]
+    feeds: Vec<String>,
     #[serde(default = "default_mpv_mode")]
     mpv_mode: bool,
     #[serde(default = "default_mpv_path")]
     mpv_path: String,
+    #[serde(default = "default_watch_later_path")]
+    watch_later_path: String,
+    #[serde(default)]
+    channel_tags: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    saved_filters: BTreeMap<String, String>,
+    #[serde(default)]
+    blocked_title_patterns: Vec<String>,
+    #[serde(default)]
+    hide_shorts: bool,
+    #[serde(default)]
+    hide_upcoming_premieres: bool,
+    #[serde(default)]
+    max_video_age_days: u64,
+    #[serde(default)]
+    max_per_channel: usize,
+    #[serde(default = "default_sort_order")]
+    sort_order: String,
+    #[serde(default)]
+    newest_first: bool,
+    #[serde(default)]
+    date_headers: bool,
+    #[serde(default = "default_date_format")]
+    date_format: String,
+    #[serde(default)]
+    row_format: String,
+    #[serde(default)]
+    row_numbers: bool,
+    #[serde(default)]
+    preview_pane: String,
+    #[serde(default)]
+    channel_sidebar: bool,
+    #[serde(default = "default_thumbnails")]
+    thumbnails: String,
+    #[serde(default)]
+    fetch_durations: bool,
+    #[serde(default)]
+    pinned_channels: Vec<String>,
+    #[serde(default)]
+    float_pinned_channels: bool,
+    #[serde(default)]
+    refresh_spread_seconds: u64,
+    #[serde(default)]
+    invidious_instances: Vec<String>,
+    #[serde(default = "default_batch_file_path")]
+    batch_file_path: String,
+    #[serde(default)]
+    batch_download: bool,
+    #[serde(default = "default_theme")]
+    theme: String,
+    #[serde(default)]
+    theme_colors: HashMap<String, String>,
+    #[serde(default)]
+    translate_command: Vec<String>,
+    #[serde(default)]
+    native_languages: Vec<String>,
+    #[serde(default)]
+    oauth_client_id: Option<String>,
+    #[serde(default)]
+    oauth_client_secret: Option<String>,
+    #[serde(default)]
+    channel_overrides: BTreeMap<String, ChannelOverride>,
+    #[serde(default)]
+    stale_after_days: u64,
+    #[serde(default)]
+    startup_mix: Option<String>,
+    #[serde(default)]
+    refetch_after_minutes: u64,
+    #[serde(default)]
+    inline: bool,
+    #[serde(default)]
+    no_color: bool,
+    #[serde(default)]
+    hyperlinks: bool,
+    #[serde(default)]
+    insecure_http: bool,
+    #[serde(default)]
+    restricted_profile: Option<RestrictedProfile>,
+    #[serde(default)]
+    proxy: Option<String>,
+    #[serde(default)]
+    daily_budget_minutes: u64,
+    #[serde(default = "default_request_timeout_seconds")]
+    request_timeout_seconds: u64,
+    #[serde(default = "default_request_retries")]
+    request_retries: u32,
+    #[serde(default = "default_retry_backoff_ms")]
+    retry_backoff_ms: u64,
+    #[serde(default)]
+    user_agent: Option<String>,
+    #[serde(default)]
+    request_headers: BTreeMap<String, String>,
+    #[serde(default)]
+    auto_refresh_minutes: u64,
+    #[serde(default)]
+    cache_max_age_hours: u64,
+    #[serde(default)]
+    case_sensitive_search: bool,
+    #[serde(default)]
+    notifications: bool,
+    #[serde(default)]
+    notify_pinned_only: bool,
+    #[serde(default = "default_notify_command")]
+    notify_command: Vec<String>,
+    #[serde(default)]
+    on_new_video: Vec<String>,
+    #[serde(default = "default_retention_days")]
+    retention_days: u64,
+    #[serde(default)]
+    cache_compression: String,
+    #[serde(skip)]
+    read_only: bool,
+    // read_only is set while either --safe-mode or presentation mode is on
+    #[serde(skip)]
+    safe_mode: bool,
+    #[serde(skip)]
+    presentation: bool,
 }
 
 impl Default for AppConfig {
@@ -54,6 +258,7 @@ impl Default for AppConfig {
         AppConfig {
             video_path: "/tmp".to_string(),
             cache_path: "/tmp/yts.json".to_string(),
+            state_path: default_state_path(),
             youtubedl_format: "[height <=? 360][ext = mp4]".to_string(),
             video_extension: "mp4".to_string(),
             players: vec![
@@ -64,52 +269,342 @@ impl Default for AppConfig {
                 vec!["/usr/bin/mplayer".to_string(), "-really-quiet".to_string(), "-fs".to_string()],
             ],
             channel_ids: vec![],
+            playlist_ids: vec![],
+            peertube_feeds: vec![],
+            odysee_channels: vec![],
+            feeds: vec![],
             mpv_mode: default_mpv_mode(),
             mpv_path: default_mpv_path(),
+            watch_later_path: default_watch_later_path(),
+            channel_tags: BTreeMap::new(),
+            saved_filters: BTreeMap::new(),
+            blocked_title_patterns: vec![],
+            hide_shorts: false,
+            hide_upcoming_premieres: false,
+            max_video_age_days: 0,
+            max_per_channel: 0,
+            sort_order: default_sort_order(),
+            newest_first: false,
+            date_headers: false,
+            date_format: default_date_format(),
+            row_format: "".to_string(),
+            row_numbers: false,
+            preview_pane: "".to_string(),
+            channel_sidebar: false,
+            thumbnails: default_thumbnails(),
+            fetch_durations: false,
+            pinned_channels: vec![],
+            float_pinned_channels: false,
+            refresh_spread_seconds: 0,
+            invidious_instances: vec![],
+            batch_file_path: default_batch_file_path(),
+            batch_download: false,
+            theme: default_theme(),
+            theme_colors: HashMap::new(),
+            translate_command: vec![],
+            native_languages: vec![],
+            oauth_client_id: None,
+            oauth_client_secret: None,
+            channel_overrides: BTreeMap::new(),
+            stale_after_days: 0,
+            startup_mix: None,
+            refetch_after_minutes: 0,
+            inline: false,
+            no_color: false,
+            hyperlinks: false,
+            insecure_http: false,
+            restricted_profile: None,
+            proxy: None,
+            daily_budget_minutes: 0,
+            request_timeout_seconds: default_request_timeout_seconds(),
+            request_retries: default_request_retries(),
+            retry_backoff_ms: default_retry_backoff_ms(),
+            user_agent: None,
+            request_headers: BTreeMap::new(),
+            auto_refresh_minutes: 0,
+            cache_max_age_hours: 0,
+            case_sensitive_search: false,
+            notifications: false,
+            notify_pinned_only: false,
+            notify_command: default_notify_command(),
+            on_new_video: vec![],
+            retention_days: default_retention_days(),
+            cache_compression: "".to_string(),
+            read_only: false,
+            safe_mode: false,
+            presentation: false,
+        }
+    }
+}
+
+type Migration = fn(&mut serde_json::Value);
+
+fn migrate_nothing(_json: &mut serde_json::Value) {
+}
+
+fn migrate_state_watched(json: &mut serde_json::Value) {
+    if json["watched"].is_null() {
+        json["watched"] = serde_json::json!([]);
+    }
+}
+
+fn migrate_cache_video_feed(json: &mut serde_json::Value) {
+    if let Some(videos) = json["videos"].as_array_mut() {
+        for video in videos.iter_mut().filter(|video| video["feed"].is_null()) {
+            video["feed"] = serde_json::json!("");
+        }
+    }
+}
+
+fn migrate_cache_video_short(json: &mut serde_json::Value) {
+    if let Some(videos) = json["videos"].as_array_mut() {
+        for video in videos.iter_mut().filter(|video| video["short"].is_null()) {
+            video["short"] = serde_json::json!(video["url"].as_str().map(|url| url.contains("/shorts/")).unwrap_or(false));
         }
     }
 }
 
+fn migrate_cache_fetched(json: &mut serde_json::Value) {
+    if json["fetched"].is_null() {
+        json["fetched"] = serde_json::json!({});
+    }
+}
+
+fn migrate_cache_validators(json: &mut serde_json::Value) {
+    if json["validators"].is_null() {
+        json["validators"] = serde_json::json!({});
+    }
+}
+
+fn migrate_state_watch_time(json: &mut serde_json::Value) {
+    if json["watch_time"].is_null() {
+        json["watch_time"] = serde_json::json!({});
+    }
+}
+
+fn migrate_state_muted(json: &mut serde_json::Value) {
+    if json["muted"].is_null() {
+        json["muted"] = serde_json::json!([]);
+    }
+}
+
+fn migrate_state_history(json: &mut serde_json::Value) {
+    if json["history"].is_null() {
+        json["history"] = serde_json::json!({});
+    }
+}
+
+// each persisted file stores the number of migrations applied to it as "version",
+// new migrations must be appended to these lists
+const CONFIG_MIGRATIONS: &[Migration] = &[migrate_nothing];
+const CACHE_MIGRATIONS: &[Migration] = &[migrate_nothing, migrate_cache_video_feed, migrate_cache_fetched, migrate_cache_validators, migrate_cache_video_short];
+const STATE_MIGRATIONS: &[Migration] = &[migrate_state_watched, migrate_state_muted, migrate_state_watch_time, migrate_state_history];
+
+// returns true when a migration changed the content, a new "version" alone
+// is not worth rewriting the file
+fn migrate(json: &mut serde_json::Value, migrations: &[Migration]) -> bool {
+    if !json.is_object() {
+        return false
+    }
+    let version = json["version"].as_u64().unwrap_or(0) as usize;
+    if version >= migrations.len() {
+        return false
+    }
+    let before = json.clone();
+    for migration in &migrations[version..] {
+        migration(json);
+    }
+    let changed = *json != before;
+    json["version"] = serde_json::json!(migrations.len());
+    changed
+}
+
+fn to_versioned_json<T: Serialize>(value: &T, migrations: &[Migration]) -> serde_json::Value {
+    let mut json = serde_json::to_value(value).unwrap();
+    json["version"] = serde_json::json!(migrations.len());
+    json
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+fn detect_compression(bytes: &[u8]) -> Compression {
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        Compression::Gzip
+    } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        Compression::Zstd
+    } else {
+        Compression::None
+    }
+}
+
+fn configured_compression(app_config: &AppConfig) -> Compression {
+    match app_config.cache_compression.as_str() {
+        "gzip" => Compression::Gzip,
+        "zstd" => Compression::Zstd,
+        _ => Compression::None,
+    }
+}
+
+// reads a file which may be gzip or zstd compressed, returns its contents and compression
+fn read_compressed(path: &str) -> io::Result<(String, Compression)> {
+    let bytes = fs::read(path)?;
+    let compression = detect_compression(&bytes);
+    let bytes = match compression {
+        Compression::Gzip => {
+            let mut decoded = vec![];
+            flate2::read::GzDecoder::new(bytes.as_slice()).read_to_end(&mut decoded)?;
+            decoded
+        },
+        Compression::Zstd => zstd::stream::decode_all(bytes.as_slice())?,
+        Compression::None => bytes,
+    };
+    let contents = String::from_utf8(bytes).map_err(|e| Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok((contents, compression))
+}
+
+fn write_compressed(path: &str, contents: &str, compression: Compression) -> io::Result<()> {
+    let bytes = match compression {
+        Compression::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+            encoder.write_all(contents.as_bytes())?;
+            encoder.finish()?
+        },
+        Compression::Zstd => zstd::stream::encode_all(contents.as_bytes(), 0)?,
+        Compression::None => contents.as_bytes().to_vec(),
+    };
+    write_atomically(path, &bytes)
+}
+
+// readers never see a partially written file
+fn write_atomically(path: &str, bytes: &[u8]) -> io::Result<()> {
+    let tmp_path = format!("{}.{}.tmp", path, std::process::id());
+    fs::write(&tmp_path, bytes)?;
+    fs::rename(tmp_path, path)
+}
+
+fn read_versioned(path: &str, migrations: &[Migration], read_only: bool) -> Option<serde_json::Value> {
+    let (contents, compression) = read_compressed(path).ok()?;
+    let mut json = serde_json::from_str::<serde_json::Value>(contents.as_str()).ok()?;
+    if migrate(&mut json, migrations) && !read_only {
+        let _res = write_compressed(path, &serde_json::to_string(&json).unwrap(), compression);
+    }
+    Some(json)
+}
+
 fn load_config() -> AppConfig {
+    try_load_config().unwrap_or_else(|e| panic!("{}", e))
+}
+
+// like load_config, but an invalid configuration is an error instead of a panic
+fn try_load_config() -> Result<AppConfig, String> {
     match dirs::home_dir() {
         Some(home) => {
             match home.to_str() {
                 Some(h) => {
                     let path = format!("{}/.config/youtube-subscriptions/config.json",
                                        h);
-                    match fs::read_to_string(path) {
+                    match fs::read_to_string(&path) {
                         Ok(s) => {
-                            match serde_json::from_str::<AppConfig>(s.as_str()) {
+                            let mut json = serde_json::from_str::<serde_json::Value>(s.as_str())
+                                .map_err(|e| format!("error parsing configuration: {:?}", e))?;
+                            if migrate(&mut json, CONFIG_MIGRATIONS) {
+                                let _res = fs::write(&path, serde_json::to_string_pretty(&json).unwrap());
+                            }
+                            match serde_json::from_value::<AppConfig>(json) {
                                 Ok(mut _res) => {
-                                    _res.video_path = _res.video_path.replace("__HOME", &h);
+                                    _res.video_path = _res.video_path.replace("__HOME", h);
                                     match fs::create_dir_all(&_res.video_path) {
                                         Ok(_) => {
-                                            _res.cache_path = _res.cache_path.replace("__HOME", &h);
+                                            _res.cache_path = _res.cache_path.replace("__HOME", h);
+                                            _res.state_path = _res.state_path.replace("__HOME", h);
+                                            _res.watch_later_path = _res.watch_later_path.replace("__HOME", h);
+                                            _res.batch_file_path = _res.batch_file_path.replace("__HOME", h);
                                             match Path::new(&_res.cache_path).parent() {
-                                                Some(dirname) => match fs::create_dir_all(&dirname) {
-                                                    Ok(_) => _res,
-                                                    Err(e) => panic!("error while creating cache directory for {}: {:?}", &_res.cache_path, e)
+                                                Some(dirname) => match fs::create_dir_all(dirname) {
+                                                    Ok(_) => Ok(_res),
+                                                    Err(e) => Err(format!("error while creating cache directory for {}: {:?}", &_res.cache_path, e))
                                                 }
-                                                None => panic!("failed to find dirname of {}", &_res.cache_path),
+                                                None => Err(format!("failed to find dirname of {}", &_res.cache_path)),
                                             }
                                         }
                                         Err(e) =>
-                                            panic!("error while creating video path {}: {:?}", &_res.video_path, e)
+                                            Err(format!("error while creating video path {}: {:?}", &_res.video_path, e))
                                     }
                                 }
-                                Err(e) => panic!("error parsing configuration: {:?}", e)
+                                Err(e) => Err(format!("error parsing configuration: {:?}", e))
                             }
                         },
                         Err(_) =>
-                            AppConfig { ..Default::default() }
+                            Ok(AppConfig { ..Default::default() })
                     }
                 }
-                None => AppConfig { ..Default::default() }
+                None => Ok(AppConfig { ..Default::default() })
             }
         },
         None =>
-            AppConfig { ..Default::default() }
+            Ok(AppConfig { ..Default::default() })
+    }
+}
+
+// the restricted profile of config.json even when the rest of it is invalid,
+// --safe-mode keeps it
+fn configured_restricted_profile() -> Option<RestrictedProfile> {
+    let json = config_path().and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|s| serde_json::from_str::<serde_json::Value>(s.as_str()).ok())?;
+    serde_json::from_value(json["restricted_profile"].clone()).ok()
+}
+
+fn config_path() -> Option<String> {
+    dirs::home_dir().and_then(|home| home.to_str().map(|h|
+        format!("{}/.config/youtube-subscriptions/config.json", h)))
+}
+
+fn update_config<F: FnOnce(&mut serde_json::Value)>(update: F) -> Result<(), Error> {
+    let path = config_path().ok_or(Error::new(NotFound, "home directory not found"))?;
+    let mut config = match fs::read_to_string(&path) {
+        Ok(s) => serde_json::from_str::<serde_json::Value>(s.as_str())?,
+        Err(_) => to_versioned_json(&AppConfig { ..Default::default() }, CONFIG_MIGRATIONS),
+    };
+    update(&mut config);
+    if let Some(dirname) = Path::new(&path).parent() {
+        fs::create_dir_all(dirname)?;
     }
+    let tmp_path = format!("{}.tmp", path);
+    fs::write(&tmp_path, serde_json::to_string_pretty(&config)?)?;
+    fs::rename(tmp_path, path)
+}
+
+fn add_channel_id_to_config(channel_id: &str) -> Result<(), Error> {
+    update_config( |config| match config["channel_ids"].as_array_mut() {
+        Some(ids) => ids.push(serde_json::Value::String(channel_id.to_string())),
+        None => config["channel_ids"] = serde_json::json!([channel_id]),
+    })
+}
+
+fn remove_feed_from_config(feed: &str) -> Result<(), Error> {
+    update_config( |config| {
+        for (key, prefix) in &[
+            ("channel_ids", "https://www.youtube.com/feeds/videos.xml?channel_id="),
+            ("playlist_ids", "https://www.youtube.com/feeds/videos.xml?playlist_id="),
+            ("odysee_channels", "https://odysee.com/$/rss/"),
+            ("peertube_feeds", ""),
+            ("feeds", ""),
+        ] {
+            if let Some(entries) = config[*key].as_array_mut() {
+                entries.retain(|entry| entry.as_str().map(|e| format!("{}{}", prefix, e) != feed).unwrap_or(true));
+            }
+        }
+    })
+}
+
+fn subscriptions_path() -> Option<String> {
+    dirs::home_dir().and_then(|home| home.to_str().map(|h|
+        format!("{}/.config/youtube-subscriptions/subscription_manager", h)))
 }
 
 fn get_subscriptions_xml() -> Result<String, Error> {
@@ -119,11 +614,11 @@ fn get_subscriptions_xml() -> Result<String, Error> {
                 Some(s) => {
                     let path = format!("{}/.config/youtube-subscriptions/subscription_manager", s);
                     if fs::metadata(&path).is_ok() {
-                        return fs::read_to_string(path)
+                        fs::read_to_string(path)
                     }
                     else {
                         let url = "https://www.youtube.com/subscription_manager?action_takeout=1";
-                        let _res = webbrowser::open(&url);
+                        let _res = webbrowser::open(url);
                         panic!("configuration is missing
 please download: {} (a browser window should be opened with it).
 make it available as {} ", url, path)
@@ -137,583 +632,4537 @@ make it available as {} ", url, path)
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct Video {
-    channel: String,
-    title: String,
-    thumbnail: String,
-    url: String,
-    published: String,
-    description: String,
+#[derive(Serialize, Deserialize, Debug)]
+struct OAuthToken {
+    refresh_token: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct Videos {
-    videos: Vec<Video>,
+fn oauth_token_path() -> Option<String> {
+    dirs::home_dir().and_then(|home| home.to_str().map(|h|
+        format!("{}/.config/youtube-subscriptions/oauth_token.json", h)))
 }
 
-fn get_value(xpath: String, node: Element) -> String {
-    let factory = Factory::new();
-    let xpath = factory.build(xpath.as_str()).expect("Could not compile XPath");
-    let xpath = xpath.expect("No XPath was compiled");
-    let context = Context::new();
-    return xpath.evaluate(&context, node).unwrap_or(Value::String("".to_string())).string().to_string();
+fn load_oauth_token() -> Option<OAuthToken> {
+    oauth_token_path().and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|s| serde_json::from_str::<OAuthToken>(s.as_str()).ok())
 }
 
-fn get_channel_videos(channel_url: String) -> Vec<Video> {
-    let response = ureq::get(channel_url.replace("https:", "http:").as_str()).call();
-    if response.ok() {
-        let contents = response.into_string().unwrap();
-                    let package = parser::parse(contents.as_str()).expect("failed to parse XML");
-                    let document = package.as_document();
-                    let title = evaluate_xpath(&document, "string(/*[local-name() = 'feed']/*[local-name() = 'title']/text())").unwrap_or(Value::String("".to_string())).string();
-                    match evaluate_xpath(&document, "/*[local-name() = 'feed']/*[local-name() = 'entry']") {
-                        Ok(val) => {
-                            if let Value::Nodeset(entries) = val {
-                                entries.iter().flat_map( |entry|
-                                     match entry.element() {
-                                         Some(_element) => 
-                                         {
-                                             vec![Video { 
-                                                 channel: title.to_string(),
-                                                 title: get_value("string(*[local-name() = 'title']/text())".to_string(), _element),
-                                                 thumbnail: get_value("string(*[local-name() = 'group']/*[local-name() = 'thumbnail']/@url)".to_string(), _element),
-                                                 url: get_value("string(*[local-name() = 'group']/*[local-name() = 'content']/@url)".to_string(), _element),
-                                                 published: get_value("string(*[local-name() = 'published']/text())".to_string(), _element),
-                                                 description: get_value("string(*[local-name() = 'group']/*[local-name() = 'description']/text())".to_string(), _element),
-                                             }]
-                                         },
-                                         None => vec![]
-                                         }
-                                ).collect()
-                            }
-                            else {
-                                vec![]
-                            }
-                        },
-                        Err(_) => {
-                            println!("aaaaa");
-                            vec![]
-                        }
-                    }
-                }
-    else {
-        vec![]
+// feeds and videos requested at the same time
+const CONCURRENT_REQUESTS: usize = 16;
+
+// runs the network requests, commands block on it and the interface spawns its fetches on it
+fn runtime() -> &'static tokio::runtime::Runtime {
+    static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| tokio::runtime::Builder::new_multi_thread().enable_all().build().expect("failed to start the async runtime"))
+}
+
+fn proxy_url(app_config: &AppConfig) -> Option<String> {
+    app_config.proxy.clone().or_else( ||
+        ["HTTPS_PROXY", "https_proxy", "HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"].iter()
+            .flat_map(|name| env::var(name).ok())
+            .find(|proxy| !proxy.is_empty()))
+}
+
+fn build_client(app_config: &AppConfig) -> Option<reqwest::Client> {
+    let mut headers = reqwest::header::HeaderMap::new();
+    for (name, value) in &app_config.request_headers {
+        if let (Ok(name), Ok(value)) = (reqwest::header::HeaderName::from_bytes(name.as_bytes()), reqwest::header::HeaderValue::from_str(value)) {
+            headers.insert(name, value);
+        }
+    }
+    let mut builder = reqwest::Client::builder()
+        .timeout(Duration::from_secs(app_config.request_timeout_seconds))
+        .default_headers(headers)
+        .no_proxy();
+    if let Some(user_agent) = &app_config.user_agent {
+        builder = builder.user_agent(user_agent.as_str());
     }
+    if let Some(proxy) = proxy_url(app_config).and_then(|proxy| reqwest::Proxy::all(proxy.as_str()).ok()) {
+        builder = builder.proxy(proxy);
+    }
+    builder.build().ok()
 }
 
-fn get_videos(xml: String, additional_channel_ids: &Vec<String>) -> Vec<Video> {
-    let package = parser::parse(xml.as_str()).expect("failed to parse XML");
-    let document = package.as_document();
-    match evaluate_xpath(&document, "//outline/@xmlUrl") {
-        Ok(value) =>  {
-            if let Value::Nodeset(urls) = value {
-                let mut urls_from_xml : Vec<String> = urls.iter().flat_map( |url| {
-                    match url.attribute() {
-                        Some(attribute) => Some(attribute.value().to_string()),
-                        None => None
-                    }
-                }).collect::<Vec<String>>();
-                let urls_from_additional = additional_channel_ids.iter().map( |id| "https://www.youtube.com/feeds/videos.xml?channel_id=".to_string() + id);
-                urls_from_xml.extend(urls_from_additional);
-                urls_from_xml.par_iter().flat_map( |url|
-                       get_channel_videos(url.to_string())
-                ).collect::<Vec<Video>>()
-            }
-            else {
-                vec![]
-            }
-        },
-        Err(err) => {
-            println!("{:?}", err);
-            vec![]
+// the client is shared by the requests (and their connections kept) until the settings change
+fn http_client(app_config: &AppConfig) -> reqwest::Client {
+    static CLIENT: Mutex<Option<(String, reqwest::Client)>> = Mutex::new(None);
+    let settings = format!("{:?}", (app_config.request_timeout_seconds, &app_config.user_agent, &app_config.request_headers, proxy_url(app_config)));
+    let mut client = CLIENT.lock().unwrap();
+    match client.as_ref() {
+        Some((known, client)) if *known == settings => client.clone(),
+        _ => {
+            let built = build_client(app_config).unwrap_or_default();
+            *client = Some((settings, built.clone()));
+            built
         }
     }
-    
 }
 
-fn to_show_videos(videos: &mut Vec<Video>, start: usize, end: usize, filter: &String) -> Vec<Video> {
-    videos.sort_by(|a, b| b.published.cmp(&a.published));
-    let filtered_videos = videos.iter().filter(|video| 
-        video.title.contains(filter.as_str()) || video.channel.contains(filter.as_str()) 
-    ).cloned().collect::<Vec<Video>>();
-    let new_end = std::cmp::min(end, filtered_videos.len());
-    let mut result = filtered_videos[start..new_end].to_vec();
-    result.reverse();
-    return result;
+fn http_get(url: &str, app_config: &AppConfig) -> reqwest::RequestBuilder {
+    http_client(app_config).get(url)
 }
 
-fn load(reload: bool, app_config: &AppConfig) -> Option<Videos> {
-    match get_subscriptions_xml() {
-        Ok(xml) => {
-            let path = app_config.cache_path.as_str();
-            if reload || !fs::metadata(path).is_ok() {
-                let videos = Videos { videos: get_videos(xml, &app_config.channel_ids)};
-                let serialized = serde_json::to_string(&videos).unwrap();
-                fs::write(path, serialized).expect("writing videos json failed");
-            }
-            match fs::read_to_string(path) {
-                Ok(s) => 
-                    Some(serde_json::from_str(s.as_str()).unwrap()),
-                Err(_) =>
-                    None
-            }
-        },
-        Err(_) =>
-            None
+// retries on network errors, 429 and 5xx responses with an exponential backoff,
+// Retry-After is honored when present, waits are at most request_timeout_seconds
+async fn call(request: reqwest::RequestBuilder, app_config: &AppConfig) -> Option<reqwest::Response> {
+    let mut backoff = app_config.retry_backoff_ms;
+    let mut attempt = 0;
+    loop {
+        let response = request.try_clone()?.send().await;
+        let retry = match &response {
+            Ok(response) => response.status().as_u16() == 429 || response.status().is_server_error(),
+            Err(_) => true,
+        };
+        if !retry || attempt >= app_config.request_retries {
+            return response.ok()
+        }
+        let wait = response.ok()
+            .and_then(|response| response.headers().get("Retry-After").and_then(|h| h.to_str().ok()).and_then(|s| s.trim().parse::<u64>().ok()))
+            .map(|s| s.saturating_mul(1000)).unwrap_or(backoff);
+        tokio::time::sleep(Duration::from_millis(min(wait, app_config.request_timeout_seconds.saturating_mul(1000)))).await;
+        backoff = backoff.saturating_mul(2);
+        attempt += 1;
     }
 }
 
+// body of a successful response
+async fn call_text(request: reqwest::RequestBuilder, app_config: &AppConfig) -> Option<String> {
+    let response = call(request, app_config).await.filter(|response| response.status().is_success())?;
+    response.text().await.ok()
+}
 
-fn get_lines() -> usize {
-    let size = terminal_size();
-    if let Some((Width(_), Height(h))) = size {
-        (h - 1) as usize
-    } else {
-        20
-    }
+async fn call_json(request: reqwest::RequestBuilder, app_config: &AppConfig) -> Option<serde_json::Value> {
+    call_text(request, app_config).await.and_then(|s| serde_json::from_str(s.as_str()).ok())
 }
 
-fn get_cols() -> usize {
-    let size = terminal_size();
-    if let Some((Width(w), Height(_))) = size {
-        w as usize
-    } else {
-        20
+// error responses are returned too, oauth tells with them why a token is not granted
+async fn post_form(url: &str, params: &[(&str, &str)], app_config: &AppConfig) -> Option<serde_json::Value> {
+    let response = http_client(app_config).post(url).form(params).send().await.ok()?;
+    response.text().await.ok().and_then(|s| serde_json::from_str(s.as_str()).ok())
+}
+
+fn oauth_client(app_config: &AppConfig) -> Result<(String, String), String> {
+    match &app_config.oauth_client_id {
+        Some(id) => Ok((id.to_string(), app_config.oauth_client_secret.clone().unwrap_or("".to_string()))),
+        None => Err("oauth_client_id is missing from configuration".to_string()),
     }
 }
 
-fn hide_cursor() {
-    print!("\x1b[?25l");
-    io::stdout().flush().unwrap();
+// the refresh token is only readable by its owner
+fn write_oauth_token(path: &str, token: &OAuthToken) -> io::Result<()> {
+    let mut file = fs::OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(path)?;
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    file.write_all(serde_json::to_string(token).unwrap().as_bytes())
 }
 
-fn smcup() {
-    print!("\x1b[?1049h");
-    io::stdout().flush().unwrap();
+async fn oauth_login(app_config: &AppConfig) {
+    let (client_id, client_secret) = match oauth_client(app_config) {
+        Ok(client) => client,
+        Err(e) => {
+            println!("{}", e);
+            std::process::exit(1)
+        }
+    };
+    let device = post_form("https://oauth2.googleapis.com/device/code", &[
+        ("client_id", &client_id),
+        ("scope", "https://www.googleapis.com/auth/youtube.readonly"),
+    ], app_config).await.expect("failed requesting device code");
+    let device_code = device["device_code"].as_str().expect("no device code in response").to_string();
+    let mut interval = device["interval"].as_u64().unwrap_or(5);
+    println!("please visit {} and enter the code {}",
+             device["verification_url"].as_str().unwrap_or("https://www.google.com/device"),
+             device["user_code"].as_str().unwrap_or(""));
+    loop {
+        tokio::time::sleep(Duration::from_secs(interval)).await;
+        let token = post_form("https://oauth2.googleapis.com/token", &[
+            ("client_id", &client_id),
+            ("client_secret", &client_secret),
+            ("device_code", &device_code),
+            ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+        ], app_config).await.expect("failed polling for token");
+        match token["refresh_token"].as_str() {
+            Some(refresh_token) => {
+                let path = oauth_token_path().expect("failed to find home directory");
+                write_oauth_token(&path, &OAuthToken { refresh_token: refresh_token.to_string() }).expect("writing oauth token failed");
+                println!("logged in, token saved to {}", path);
+                return
+            },
+            None => match token["error"].as_str() {
+                Some("authorization_pending") => (),
+                Some("slow_down") => interval += 5,
+                Some(e) => panic!("login failed: {}", e),
+                None => panic!("login failed: {}", token),
+            }
+        }
+    }
 }
 
-fn rmcup() {
-    print!("\x1b[?1049l");
-    io::stdout().flush().unwrap();
+async fn oauth_access_token(token: &OAuthToken, app_config: &AppConfig) -> Option<String> {
+    let (client_id, client_secret) = oauth_client(app_config).ok()?;
+    post_form("https://oauth2.googleapis.com/token", &[
+        ("client_id", &client_id),
+        ("client_secret", &client_secret),
+        ("refresh_token", &token.refresh_token),
+        ("grant_type", "refresh_token"),
+    ], app_config).await.and_then(|v| v["access_token"].as_str().map(|s| s.to_string()))
 }
 
-fn clear() {
-    print!("\x1b[2J");
-    io::stdout().flush().unwrap();
+async fn get_oauth_channel_ids(token: &OAuthToken, app_config: &AppConfig) -> Vec<String> {
+    let mut ids = vec![];
+    let access_token = match oauth_access_token(token, app_config).await {
+        Some(t) => t,
+        None => return ids,
+    };
+    let mut page_token = "".to_string();
+    loop {
+        let request = http_get("https://www.googleapis.com/youtube/v3/subscriptions", app_config)
+            .query(&[("part", "snippet"), ("mine", "true"), ("maxResults", "50"), ("pageToken", &page_token)])
+            .bearer_auth(&access_token);
+        let page = match call_json(request, app_config).await {
+            Some(page) => page,
+            None => break,
+        };
+        if let Some(items) = page["items"].as_array() {
+            ids.extend(items.iter().flat_map(|item|
+                item["snippet"]["resourceId"]["channelId"].as_str().map(|s| s.to_string())));
+        }
+        match page["nextPageToken"].as_str() {
+            Some(next) => page_token = next.to_string(),
+            None => break,
+        }
+    }
+    ids
 }
 
-fn show_cursor() {
-    print!("\x1b[?25h");
-    io::stdout().flush().unwrap();
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Video {
+    channel: String,
+    title: String,
+    thumbnail: String,
+    url: String,
+    published: String,
+    description: String,
+    #[serde(default)]
+    feed: String,
+    #[serde(default)]
+    short: bool,
+    // seconds
+    #[serde(default)]
+    duration: Option<u64>,
+    #[serde(default)]
+    views: Option<u64>,
+    #[serde(default)]
+    likes: Option<u64>,
+    // subscriber count of the channel, as shown by invidious (for example `1.2M`)
+    #[serde(default)]
+    subscribers: Option<String>,
+    #[serde(default)]
+    live: bool,
+    // scheduled start of an upcoming premiere
+    #[serde(default)]
+    premiere: Option<i64>,
+    // when the video was first fetched
+    #[serde(default)]
+    added: Option<i64>,
 }
 
-fn move_cursor(i: usize) {
-    print!("\x1b[{};0f", i + 1);
-    io::stdout().flush().unwrap();
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Videos {
+    videos: Vec<Video>,
+    // last successful fetch timestamp of each feed url
+    #[serde(default)]
+    fetched: HashMap<String, i64>,
+    #[serde(default)]
+    validators: HashMap<String, Validator>,
 }
 
-fn move_to_bottom() {
-    print!("\x1b[{};0f", get_lines() + 1);
-    io::stdout().flush().unwrap();
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct MutedChannel {
+    channel: String,
+    until: Option<i64>,
 }
 
-fn clear_to_end_of_line() {
-    print!("\x1b[K");
-    io::stdout().flush().unwrap();
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+struct State {
+    #[serde(default)]
+    watched: HashSet<String>,
+    #[serde(default)]
+    muted: Vec<MutedChannel>,
+    // seconds of playback for each day (YYYY-MM-DD)
+    #[serde(default)]
+    watch_time: BTreeMap<String, u64>,
+    // previous `/`, `f` and `:` inputs by prompt, oldest first
+    #[serde(default)]
+    history: BTreeMap<String, Vec<String>>,
+    // the state as last read or written, save_state applies the changes made
+    // since then to the file, which other instances may have written meanwhile
+    #[serde(skip)]
+    saved: Option<Box<State>>,
 }
 
-fn debug(s: &String) {
-    move_to_bottom();
-    clear_to_end_of_line();
-    move_to_bottom();
-    print!("{}", s);
-    io::stdout().flush().unwrap();
+const MAX_HISTORY: usize = 100;
+
+fn today() -> String {
+    format_timestamp(now_timestamp())[..10].to_string()
 }
 
-fn print_selector(i: usize) {
-    move_cursor(i);
-    print!("\x1b[1m|\x1b[0m\r");
-    io::stdout().flush().unwrap();
+/// minutes left of daily_budget_minutes, None when there is no budget
+fn remaining_budget(state: &State, app_config: &AppConfig) -> Option<i64> {
+    if app_config.daily_budget_minutes == 0 {
+        return None
+    }
+    let watched = state.watch_time.get(&today()).cloned().unwrap_or(0) as i64;
+    Some(app_config.daily_budget_minutes as i64 - watched / 60)
 }
 
-fn clear_selector(i: usize) {
-    move_cursor(i);
-    print!(" ");
-    io::stdout().flush().unwrap();
+fn is_muted(video: &Video, state: &State, now: i64) -> bool {
+    state.muted.iter().any(|m| m.channel == video.channel && m.until.map(|until| until > now).unwrap_or(true))
 }
 
-fn jump(i: usize, new_i: usize) -> usize {
-    clear_selector(i);
-    return new_i;
+// exclusive lock shared by the running instances on a file, released when dropped
+struct FileLock {
+    _file: fs::File,
 }
 
-fn pause() {
-    let input = input();
-    let _screen = RawScreen::into_raw_mode();
-    let _c = input.read_char();
+// waits for other instances writing path, None if the lock file can't be used
+fn lock_file(path: &str) -> Option<FileLock> {
+    let file = fs::OpenOptions::new().create(true).write(true).open(format!("{}.lock", path)).ok()?;
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+        return None
+    }
+    Some(FileLock { _file: file })
 }
 
-struct YoutubeSubscribtions {
-    n: usize,
-    start: usize,
-    filter: String,
-    i: usize,
-    toshow: Vec<Video>,
-    videos: Videos,
-    app_config: AppConfig,
+fn read_state(app_config: &AppConfig) -> Option<State> {
+    read_versioned(&app_config.state_path, STATE_MIGRATIONS, app_config.read_only)
+        .and_then(|json| serde_json::from_value(json).ok())
 }
 
-fn print_videos(toshow: &Vec<Video>) {
-    let max = toshow.iter().fold(0, |acc, x| if x.channel.chars().count() > acc { x.channel.chars().count() } else { acc } );
-    let cols = get_cols();
-    for video in toshow {
-        let published = video.published.split("T").collect::<Vec<&str>>();
-        let whitespaces = " ".repeat(max - video.channel.chars().count());
-        let s = format!("  \x1b[36m{}\x1b[0m \x1b[34m{}\x1b[0m{} {}", published[0][5..10].to_string(), video.channel, whitespaces, video.title);
-        println!("{}", s.chars().take(min(s.chars().count(), cols-4+9+9+2)).collect::<String>());
+fn load_state(app_config: &AppConfig) -> State {
+    let mut state = read_state(app_config).unwrap_or(State { ..Default::default() });
+    state.saved = Some(Box::new(state.clone()));
+    state
+}
+
+// applies the changes made to state since it was saved to on_disk
+fn merge_state(state: &State, mut on_disk: State) -> State {
+    let saved = state.saved.as_deref().cloned().unwrap_or_default();
+    for id in state.watched.difference(&saved.watched) {
+        on_disk.watched.insert(id.to_string());
+    }
+    for id in saved.watched.difference(&state.watched) {
+        on_disk.watched.remove(id);
+    }
+    on_disk.muted.retain(|muted| !saved.muted.iter().any(|m| m.channel == muted.channel)
+        || state.muted.iter().any(|m| m.channel == muted.channel));
+    for muted in state.muted.iter().filter(|muted| !saved.muted.contains(muted)) {
+        on_disk.muted.retain(|m| m.channel != muted.channel);
+        on_disk.muted.push(muted.clone());
+    }
+    for (day, seconds) in &state.watch_time {
+        let played = seconds.saturating_sub(saved.watch_time.get(day).cloned().unwrap_or(0));
+        *on_disk.watch_time.entry(day.to_string()).or_insert(0) += played;
     }
+    for (prompt, lines) in &state.history {
+        let previous = saved.history.get(prompt).cloned().unwrap_or_default();
+        let history = on_disk.history.entry(prompt.to_string()).or_default();
+        for line in lines.iter().filter(|line| !previous.contains(line)) {
+            history.retain(|previous| previous != line);
+            history.push(line.to_string());
+        }
+        let excess = history.len().saturating_sub(MAX_HISTORY);
+        history.drain(..excess);
+    }
+    on_disk
 }
 
-fn get_id(v: &Video) -> Option<Option<String>> {
-    v.url.split("/").collect::<Vec<&str>>().last().map( |page|
-                                                        page.split("?").collect::<Vec<&str>>().first().map( |s| s.to_string() ))
+fn save_state(state: &mut State, app_config: &AppConfig) {
+    if app_config.read_only {
+        return
+    }
+    let _lock = lock_file(&app_config.state_path);
+    if let Some(on_disk) = read_state(app_config) {
+        *state = merge_state(state, on_disk);
+    }
+    let serialized = serde_json::to_string(&to_versioned_json(state, STATE_MIGRATIONS)).unwrap();
+    write_atomically(&app_config.state_path, serialized.as_bytes()).expect("writing state json failed");
+    state.saved = None;
+    state.saved = Some(Box::new(state.clone()));
 }
 
-fn read_command_output(command: &mut Command, binary: &String) {
-    match command.stdout(Stdio::piped())
-        .spawn() {
-            Ok(spawn) => {
-                match spawn.stdout {
-                    Some(stdout) => {
-                        for byte in stdout.bytes() {
-                            print!("{}", byte.unwrap() as char);
-                            io::stdout().flush().unwrap();
-                        }
-                    },
-                    None => ()
+fn tokenize(expression: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in expression.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            ' ' if !quoted => {
+                if !current.is_empty() {
+                    tokens.push(current.to_string());
+                    current.clear();
                 }
             },
-            Err(e) => {
-                if let NotFound = e.kind() {
-                    println!("`{}` was not found: maybe you should install it ?", binary)
-                } else {
-                    println!("error while runnnig {} : {}", binary, e);
-                }
-                pause();
-            }
+            _ => current.push(c),
         }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
 }
 
-fn play_video(path: &String, app_config: &AppConfig) {
-    for player in &app_config.players {
-        if fs::metadata(&player[0]).is_ok() {
-
-            let mut child1 = Command::new(&player[0]);
-            for i in 1..player.len() {
-                child1.arg(&player[i]);
-            } 
-            read_command_output(child1.arg(path), &player[0]);
-            return
+/// matches expressions like `channel:"Some Channel" after:2020-01-01 before:2020-02-01 keyword`
+fn matches_expression(video: &Video, expression: &str) -> bool {
+    let expression = expression.trim();
+    let expression = expression.strip_prefix("filter:").unwrap_or(expression);
+    tokenize(expression).iter().all( |token| {
+        let (negated, token) = negated_filter(token);
+        negated != match token.splitn(2, ':').collect::<Vec<&str>>().as_slice() {
+            ["channel", channel] => video.channel.contains(channel),
+            ["after", date] => video.published.as_str() >= *date,
+            ["before", date] => video.published.as_str() < *date,
+            ["re", _] => text_matcher(token, true)(&video.title),
+            _ => matches_duration(video, token).unwrap_or_else(|| video.title.contains(token)),
+        }
+    })
+}
+
+fn set_watched_matching(state: &mut State, videos: &[Video], expression: &str, watched: bool) -> usize {
+    let mut count = 0;
+    for video in videos.iter().filter(|video| matches_expression(video, expression)) {
+        if let Some(Some(id)) = get_id(video) {
+            let changed = if watched { state.watched.insert(id) } else { state.watched.remove(&id) };
+            if changed {
+                count += 1;
+            }
         }
     }
+    count
 }
 
-fn download_video(path: &String, id: &String, app_config: &AppConfig) {
-    if !fs::metadata(&path).is_ok() {
-        read_command_output(Command::new("youtube-dl")
-            .arg("-f")
-            .arg(&app_config.youtubedl_format)
-            .arg("-o")
-            .arg(&path)
-            .arg("--")
-            .arg(&id), &"youtube-dl".to_string())
+fn import_archive(archive_path: &String, app_config: &AppConfig) {
+    let archive = fs::read_to_string(archive_path).expect("failed reading download archive");
+    let mut state = load_state(app_config);
+    let mut count = 0;
+    for line in archive.lines() {
+        let fields = line.split_whitespace().collect::<Vec<&str>>();
+        if fields.len() == 2 && fields[0] == "youtube" && state.watched.insert(fields[1].to_string()) {
+            count += 1;
+        }
     }
+    save_state(&mut state, app_config);
+    println!("imported {} videos from {}", count, archive_path);
 }
 
-fn play_id(id: &String, app_config: &AppConfig) {
-    if app_config.mpv_mode && fs::metadata(&app_config.mpv_path).is_ok() {
-        let url = format!("https://www.youtube.com/watch?v={}", id);
-        let message = format!("playing {} with mpv...", url);
-        debug(&message);
-        read_command_output(
-            Command::new(&app_config.mpv_path)
-            .arg("-fs")
-            .arg("-really-quiet")
-            .arg("--ytdl-format")
-            .arg(&app_config.youtubedl_format)
-            .arg(url)
-            , &app_config.mpv_path);
+fn get_value(xpath: String, node: Element) -> String {
+    let factory = Factory::new();
+    let xpath = factory.build(xpath.as_str()).expect("Could not compile XPath");
+    let xpath = xpath.expect("No XPath was compiled");
+    let context = Context::new();
+    return xpath.evaluate(&context, node).unwrap_or(Value::String("".to_string())).string().to_string();
+}
+
+/// converts an RFC 2822 date like "Tue, 07 Dec 2021 10:00:00 +0100" to RFC 3339 in UTC
+fn parse_rfc2822_date(date: &str) -> Option<String> {
+    let date = date.trim();
+    // the day of the week is optional
+    let date = match date.split_once(',') {
+        Some((_, rest)) => rest,
+        None => date,
+    };
+    let fields = date.split_whitespace().collect::<Vec<&str>>();
+    if fields.len() < 4 {
+        return None
+    }
+    let months = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+    let month = months.iter().position(|m| fields[1].eq_ignore_ascii_case(m))? as i64 + 1;
+    let day = fields[0].parse::<i64>().ok().filter(|day| (1..=31).contains(day))?;
+    let year = match fields[2].parse::<i64>().ok()? {
+        year if year < 50 => year + 2000,
+        year if year < 1000 => year + 1900,
+        year => year,
+    };
+    let time = fields[3].split(':').map(|part| part.parse::<i64>().ok()).collect::<Option<Vec<i64>>>()?;
+    let seconds = match time.as_slice() {
+        [hours, minutes] => hours * 3600 + minutes * 60,
+        [hours, minutes, seconds] => hours * 3600 + minutes * 60 + seconds,
+        _ => return None,
+    };
+    let offset = match fields.get(4).copied().unwrap_or("GMT") {
+        zone if zone.len() == 5 && (zone.starts_with('+') || zone.starts_with('-')) => {
+            let minutes = zone[1..3].parse::<i64>().ok()? * 60 + zone[3..5].parse::<i64>().ok()?;
+            if zone.starts_with('-') { -minutes } else { minutes }
+        },
+        "EDT" => -4 * 60,
+        "EST" | "CDT" => -5 * 60,
+        "CST" | "MDT" => -6 * 60,
+        "MST" | "PDT" => -7 * 60,
+        "PST" => -8 * 60,
+        // UT, GMT, Z and the other names are UTC, as RFC 2822 says
+        _ => 0,
+    };
+    Some(format_timestamp(days_from_civil(year, month, day) * 86400 + seconds - offset * 60))
+}
+
+fn get_first_value(xpaths: &[&str], node: Element) -> String {
+    xpaths.iter().map(|xpath| get_value(xpath.to_string(), node))
+        .find(|value| !value.is_empty())
+        .unwrap_or("".to_string())
+}
+
+/// decodes the html entities left in feed texts (`&amp;`, `&#39;`, `&#x27;`...)
+fn decode_entities(s: &str) -> String {
+    let mut result = String::new();
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').filter(|end| *end <= 10).and_then( |end| {
+            let entity = &rest[1..end];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => entity.strip_prefix("#x").or(entity.strip_prefix("#X"))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| entity.strip_prefix('#').and_then(|decimal| decimal.parse::<u32>().ok()))
+                    .and_then(std::char::from_u32),
+            };
+            c.map(|c| (c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            },
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+fn get_rss_videos(document: &sxd_document::dom::Document) -> Vec<Video> {
+    let title = decode_entities(&evaluate_xpath(document, "string(/rss/channel/title)").unwrap_or(Value::String("".to_string())).string());
+    match evaluate_xpath(document, "/rss/channel/item") {
+        Ok(Value::Nodeset(items)) => items.iter().flat_map( |item|
+            // items without a valid date are skipped
+            item.element().and_then( |element| Some(Video {
+                channel: title.to_string(),
+                title: decode_entities(&get_value("string(title)".to_string(), element)),
+                thumbnail: get_first_value(&[
+                    "string(.//*[local-name() = 'thumbnail']/@url)",
+                    "string(*[local-name() = 'image']/@href)",
+                ], element),
+                url: get_first_value(&["string(enclosure/@url)", "string(link/text())"], element),
+                published: parse_rfc2822_date(&get_value("string(pubDate/text())".to_string(), element))?,
+                description: decode_entities(&get_value("string(description)".to_string(), element)),
+                feed: "".to_string(),
+                short: false,
+                duration: parse_duration(&get_value("string(*[local-name() = 'duration']/text())".to_string(), element)),
+                views: None,
+                likes: None,
+                subscribers: None,
+                live: false,
+                premiere: None,
+                added: None,
+            }))
+        ).collect(),
+        _ => vec![],
+    }
+}
+
+fn get_atom_videos(document: &sxd_document::dom::Document) -> Vec<Video> {
+    let title = decode_entities(&evaluate_xpath(document, "string(/*[local-name() = 'feed']/*[local-name() = 'title'])").unwrap_or(Value::String("".to_string())).string());
+    match evaluate_xpath(document, "/*[local-name() = 'feed']/*[local-name() = 'entry']") {
+        Ok(Value::Nodeset(entries)) => entries.iter().flat_map( |entry|
+            entry.element().map( |element| Video {
+                channel: title.to_string(),
+                title: decode_entities(&get_value("string(*[local-name() = 'title'])".to_string(), element)),
+                thumbnail: get_first_value(&[
+                    "string(*[local-name() = 'group']/*[local-name() = 'thumbnail']/@url)",
+                    "string(*[local-name() = 'thumbnail']/@url)",
+                ], element),
+                url: get_first_value(&[
+                    "string(*[local-name() = 'group']/*[local-name() = 'content']/@url)",
+                    "string(*[local-name() = 'link'][@rel = 'enclosure']/@href)",
+                    "string(*[local-name() = 'link']/@href)",
+                ], element),
+                published: get_first_value(&[
+                    "string(*[local-name() = 'published']/text())",
+                    "string(*[local-name() = 'updated']/text())",
+                ], element),
+                description: decode_entities(&get_first_value(&[
+                    "string(*[local-name() = 'group']/*[local-name() = 'description'])",
+                    "string(*[local-name() = 'summary'])",
+                    "string(*[local-name() = 'content'])",
+                ], element)),
+                feed: "".to_string(),
+                short: get_value("string(*[local-name() = 'link'][@rel = 'alternate']/@href)".to_string(), element).contains("/shorts/"),
+                duration: None,
+                views: get_value("string(*[local-name() = 'group']/*[local-name() = 'community']/*[local-name() = 'statistics']/@views)".to_string(), element).parse().ok(),
+                likes: get_value("string(*[local-name() = 'group']/*[local-name() = 'community']/*[local-name() = 'starRating']/@count)".to_string(), element).parse().ok(),
+                subscribers: None,
+                live: false,
+                premiere: None,
+                added: None,
+            })
+        ).collect(),
+        _ => vec![],
+    }
+}
+
+fn parse_feed(contents: &str) -> Vec<Video> {
+    match parser::parse(contents) {
+        Ok(package) => {
+            let document = package.as_document();
+            match evaluate_xpath(&document, "boolean(/rss)") {
+                Ok(Value::Boolean(true)) => get_rss_videos(&document),
+                _ => get_atom_videos(&document),
+            }
+        },
+        Err(_) => vec![],
+    }
+}
+
+/// HTTP cache validators of a feed, sent back on the next fetch
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct Validator {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+enum Fetched {
+    Videos(Vec<Video>, Validator),
+    NotModified,
+    Failed,
+}
+
+async fn fetch_channel_videos(channel_url: &str, validator: Option<&Validator>, app_config: &AppConfig) -> Fetched {
+    let url = if app_config.insecure_http { channel_url.replace("https:", "http:") } else { channel_url.to_string() };
+    let mut request = http_get(url.as_str(), app_config);
+    if let Some(etag) = validator.and_then(|v| v.etag.as_ref()) {
+        request = request.header("If-None-Match", etag);
+    }
+    if let Some(last_modified) = validator.and_then(|v| v.last_modified.as_ref()) {
+        request = request.header("If-Modified-Since", last_modified);
+    }
+    let response = match call(request, app_config).await {
+        Some(response) => response,
+        None => return Fetched::Failed,
+    };
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        return Fetched::NotModified
+    }
+    if !response.status().is_success() {
+        return Fetched::Failed
+    }
+    let header = |name: &str| response.headers().get(name).and_then(|h| h.to_str().ok()).map(|h| h.to_string());
+    let validator = Validator {
+        etag: header("ETag"),
+        last_modified: header("Last-Modified"),
+    };
+    match response.text().await {
+        Ok(contents) => Fetched::Videos(parse_feed(contents.as_str()), validator),
+        Err(_) => Fetched::Failed,
+    }
+}
+
+async fn get_channel_videos(channel_url: String, app_config: &AppConfig) -> Vec<Video> {
+    match fetch_channel_videos(&channel_url, None, app_config).await {
+        Fetched::Videos(videos, _) => videos,
+        _ => vec![],
+    }
+}
+
+fn format_timestamp(timestamp: i64) -> String {
+    let days = timestamp.div_euclid(86400);
+    let seconds = timestamp.rem_euclid(86400);
+    // civil from days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}+00:00", year, month, day, seconds / 3600, seconds % 3600 / 60, seconds % 60)
+}
+
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// parses an RFC 3339 date like "2019-06-01T10:20:30+02:00" to a unix timestamp
+fn parse_timestamp(date: &str) -> Option<i64> {
+    let field = |range: std::ops::Range<usize>| date.get(range).and_then(|s| s.parse::<i64>().ok());
+    let days = days_from_civil(field(0..4)?, field(5..7)?, field(8..10)?);
+    let seconds = field(11..13).unwrap_or(0) * 3600 + field(14..16).unwrap_or(0) * 60 + field(17..19).unwrap_or(0);
+    let offset = match date.get(19..).map(|tz| tz.trim_start_matches(|c: char| c == '.' || c.is_ascii_digit())) {
+        Some(tz) if tz.len() >= 6 && (tz.starts_with('+') || tz.starts_with('-')) => {
+            let sign = if tz.starts_with('-') { -1 } else { 1 };
+            sign * (tz[1..3].parse::<i64>().unwrap_or(0) * 3600 + tz[4..6].parse::<i64>().unwrap_or(0) * 60)
+        },
+        _ => 0,
+    };
+    Some(days * 86400 + seconds - offset)
+}
+
+fn now_timestamp() -> i64 {
+    SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0)
+}
+
+/// formats a timestamp in the local timezone with a strftime format
+fn format_local_date(timestamp: i64, format: &str) -> String {
+    let format = match std::ffi::CString::new(format) {
+        Ok(format) => format,
+        Err(_) => return "".to_string(),
+    };
+    let time = timestamp as libc::time_t;
+    let mut buffer = [0u8; 256];
+    let len = unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        libc::localtime_r(&time, &mut tm);
+        libc::strftime(buffer.as_mut_ptr() as *mut libc::c_char, buffer.len(), format.as_ptr(), &tm)
+    };
+    String::from_utf8_lossy(&buffer[..len]).to_string()
+}
+
+fn format_age(seconds: i64) -> String {
+    match seconds {
+        s if s < 3600 => format!("{}m", s.max(0) / 60),
+        s if s < 86400 => format!("{}h", s / 3600),
+        s if s < 86400 * 14 => format!("{}d", s / 86400),
+        s if s < 86400 * 365 => format!("{}w", s / (86400 * 7)),
+        s => format!("{}y", s / (86400 * 365)),
+    }
+}
+
+/// parses durations like `1:02:03`, `02:03` or `123` to seconds
+fn parse_duration(s: &str) -> Option<u64> {
+    s.trim().split(':').try_fold(0, |seconds, part| part.parse::<u64>().ok().map(|n| seconds * 60 + n))
+}
+
+fn format_duration(seconds: u64) -> String {
+    if seconds >= 3600 {
+        format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
     } else {
-        clear();
-        move_cursor(0);
-        let path = format!("{}/{}.{}", app_config.video_path, id, app_config.video_extension);
-        download_video(&path, &id, app_config);
-        play_video(&path, app_config);
+        format!("{}:{:02}", seconds / 60, seconds % 60)
     }
 }
 
-fn play(v: &Video, app_config: &AppConfig) {
-    match get_id(v) {
-        Some(Some(id)) => {
-            play_id(&id, app_config);
-            ()
+/// duration of a youtube video from invidious_instances, or else youtube-dl
+async fn get_video_duration(video: &Video, app_config: &AppConfig) -> Option<u64> {
+    let id = match get_id(video) {
+        Some(Some(id)) => id,
+        _ => return None,
+    };
+    for instance in &app_config.invidious_instances {
+        let url = format!("{}/api/v1/videos/{}", instance.trim_end_matches('/'), id);
+        let request = http_get(url.as_str(), app_config).query(&[("fields", "lengthSeconds")]);
+        if let Some(seconds) = call_json(request, app_config).await.and_then(|json| json["lengthSeconds"].as_u64()) {
+            return Some(seconds)
+        }
+    }
+    let proxy = proxy_url(app_config);
+    let output = tokio::task::spawn_blocking(move || Command::new("youtube-dl")
+        .args(proxy.iter().flat_map(|proxy| vec!["--proxy".to_string(), proxy.to_string()]))
+        .arg("--get-duration")
+        .arg("--")
+        .arg(&id)
+        .stderr(Stdio::null())
+        .output()).await.ok()?.ok()?;
+    parse_duration(&String::from_utf8_lossy(&output.stdout))
+}
+
+// views, likes and subscribers
+type Statistics = (Option<u64>, Option<u64>, Option<String>);
+
+/// view count, like count and channel subscriber count of a youtube video from invidious_instances
+async fn get_video_statistics(video: &Video, app_config: &AppConfig) -> Option<Statistics> {
+    let id = match get_id(video) {
+        Some(Some(id)) => id,
+        _ => return None,
+    };
+    for instance in &app_config.invidious_instances {
+        let url = format!("{}/api/v1/videos/{}", instance.trim_end_matches('/'), id);
+        let request = http_get(url.as_str(), app_config).query(&[("fields", "viewCount,likeCount,subCountText")]);
+        if let Some(json) = call_json(request, app_config).await {
+            return Some((json["viewCount"].as_u64(), json["likeCount"].as_u64(), json["subCountText"].as_str().map(|s| s.to_string())))
+        }
+    }
+    None
+}
+
+fn format_count(count: u64) -> String {
+    match count {
+        c if c < 1000 => c.to_string(),
+        c if c < 1000000 => format!("{:.1}K", c as f64 / 1000.0),
+        c if c < 1000000000 => format!("{:.1}M", c as f64 / 1000000.0),
+        c => format!("{:.1}B", c as f64 / 1000000000.0),
+    }
+}
+
+// youtube videos of the cache whose duration is not known yet
+fn missing_durations(videos: &Videos) -> Vec<Video> {
+    videos.videos.iter().filter(|video| video.duration.is_none() && is_youtube(video)).cloned().collect()
+}
+
+async fn get_durations(videos: &[Video], app_config: &AppConfig) -> Vec<(String, u64)> {
+    stream::iter(videos.iter().cloned())
+        .map(|video| async move { get_video_duration(&video, app_config).await.map(|duration| (video.url, duration)) })
+        .buffer_unordered(CONCURRENT_REQUESTS)
+        .filter_map(|duration| async move { duration })
+        .collect().await
+}
+
+fn set_durations(videos: &mut Videos, durations: Vec<(String, u64)>) {
+    let durations = durations.into_iter().collect::<HashMap<String, u64>>();
+    for video in videos.videos.iter_mut() {
+        if let Some(duration) = durations.get(&video.url) {
+            video.duration = Some(*duration);
+        }
+    }
+}
+
+async fn get_invidious_channel_videos(channel_id: &str, app_config: &AppConfig) -> Vec<Video> {
+    get_invidious_channel_page(channel_id, None, app_config).await.map(|(videos, _)| videos).unwrap_or(vec![])
+}
+
+/// one page of channel videos and the continuation token of the next (older) page
+async fn get_invidious_channel_page(channel_id: &str, continuation: Option<&str>, app_config: &AppConfig) -> Option<(Vec<Video>, Option<String>)> {
+    for instance in &app_config.invidious_instances {
+        let url = format!("{}/api/v1/channels/{}/videos", instance.trim_end_matches('/'), channel_id);
+        let mut request = http_get(url.as_str(), app_config);
+        if let Some(continuation) = continuation {
+            request = request.query(&[("continuation", continuation)]);
+        }
+        let json = match call_json(request, app_config).await {
+            Some(json) => json,
+            None => continue,
+        };
+        let entries = match json.as_array().or(json["videos"].as_array()) {
+            Some(entries) => entries.clone(),
+            None => continue,
+        };
+        let videos = entries.iter().flat_map( |entry|
+            entry["videoId"].as_str().map( |id| Video {
+                channel: entry["author"].as_str().unwrap_or("").to_string(),
+                title: entry["title"].as_str().unwrap_or("").to_string(),
+                thumbnail: format!("https://i.ytimg.com/vi/{}/hqdefault.jpg", id),
+                url: format!("https://www.youtube.com/v/{}?version=3", id),
+                published: format_timestamp(entry["published"].as_i64().unwrap_or(0)),
+                description: entry["description"].as_str().unwrap_or("").to_string(),
+                feed: "".to_string(),
+                short: false,
+                duration: entry["lengthSeconds"].as_u64(),
+                views: entry["viewCount"].as_u64(),
+                likes: None,
+                subscribers: None,
+                live: entry["liveNow"].as_bool().unwrap_or(false),
+                premiere: entry["premiereTimestamp"].as_i64().filter(|_| entry["isUpcoming"].as_bool().unwrap_or(false)),
+                added: None,
+            })
+        ).collect();
+        return Some((videos, json["continuation"].as_str().map(|c| c.to_string())))
+    }
+    None
+}
+
+async fn search_channels(query: &str, app_config: &AppConfig) -> Vec<(String, String, u64)> {
+    for instance in &app_config.invidious_instances {
+        let url = format!("{}/api/v1/search", instance.trim_end_matches('/'));
+        let request = http_get(url.as_str(), app_config).query(&[("q", query), ("type", "channel")]);
+        if let Some(results) = call_json(request, app_config).await {
+            if let Some(results) = results.as_array() {
+                return results.iter().flat_map( |result|
+                    result["authorId"].as_str().map( |id| (
+                        result["author"].as_str().unwrap_or("").to_string(),
+                        id.to_string(),
+                        result["subCount"].as_u64().unwrap_or(0),
+                    ))
+                ).collect()
+            }
+        }
+    }
+    vec![]
+}
+
+async fn fetch_feed_videos(url: &str, validator: Option<&Validator>, app_config: &AppConfig) -> Fetched {
+    let fetched = match url.split("channel_id=").nth(1) {
+        Some(channel_id) if !app_config.invidious_instances.is_empty() =>
+            match get_invidious_channel_videos(channel_id, app_config).await {
+                videos if videos.is_empty() => Fetched::Failed,
+                videos => Fetched::Videos(videos, Validator::default()),
+            },
+        _ => fetch_channel_videos(url, validator, app_config).await,
+    };
+    match fetched {
+        Fetched::Videos(mut videos, validator) => {
+            for video in videos.iter_mut() {
+                video.feed = url.to_string();
+            }
+            Fetched::Videos(videos, validator)
         },
-        _ => (),
+        other => other,
     }
 }
 
-fn print_help() {
-    println!("
-  youtube-subscriptions: a tool to view your youtube subscriptions in a terminal
+async fn get_feed_videos(url: &str, app_config: &AppConfig) -> Vec<Video> {
+    match fetch_feed_videos(url, None, app_config).await {
+        Fetched::Videos(videos, _) => videos,
+        _ => vec![],
+    }
+}
 
-  q          quit
-  j,l,down   move down
-  k,up       move up
-  g,H        go to top
-  G,L        go to bottom
-  M          go to middle
-  r,$,left   soft refresh
-  P          previous page
-  N          next page
-  R          full refresh (fetches video list)
-  h,?        prints this help
-  i,right    prints video information
-  /          search
-  f          filter
-  p,enter    plays selected video
-  o          open selected video in browser
-  ")
+fn random_fraction(seed: &str) -> f64 {
+    RandomState::new().hash_one(seed) as f64 / u64::MAX as f64
 }
 
-fn print_info(v: &Video) {
-    println!("{}", v.title);
-    println!("");
-    println!("from {}", v.channel);
-    println!("");
-    println!("{}", v.description);
+async fn get_videos_spread<T, F: Future<Output = T>>(urls: &[String], fetch: impl Fn(String) -> F, app_config: &AppConfig) -> Vec<T> {
+    let spread = app_config.refresh_spread_seconds as f64;
+    let mut schedule = urls.iter().map( |url|
+        (Duration::from_millis((random_fraction(url) * spread * 1000.0) as u64), url)
+    ).collect::<Vec<(Duration, &String)>>();
+    schedule.sort();
+    let start = tokio::time::Instant::now();
+    let mut fetched = vec![];
+    for (offset, url) in schedule {
+        tokio::time::sleep_until(start + offset).await;
+        fetched.push(fetch(url.to_string()).await);
+    }
+    fetched
 }
 
-fn quit() {
-    show_cursor();
-    rmcup();
+fn get_feed_urls(xml: &str, additional_channel_ids: &[String], app_config: &AppConfig) -> Vec<String> {
+    let mut urls = get_opml_outlines(xml).into_iter().map(|(_, url)| url).collect::<Vec<String>>();
+    let urls_from_additional = additional_channel_ids.iter().map( |id| "https://www.youtube.com/feeds/videos.xml?channel_id=".to_string() + id);
+    urls.extend(urls_from_additional);
+    let urls_from_playlists = app_config.playlist_ids.iter().map( |id| "https://www.youtube.com/feeds/videos.xml?playlist_id=".to_string() + id);
+    urls.extend(urls_from_playlists);
+    urls.extend(app_config.peertube_feeds.iter().cloned());
+    let urls_from_odysee = app_config.odysee_channels.iter().map( |name| "https://odysee.com/$/rss/".to_string() + name);
+    urls.extend(urls_from_odysee);
+    urls.extend(app_config.feeds.iter().cloned());
+    // a channel can be both in the subscriptions and in channel_ids
+    let mut seen = HashSet::new();
+    urls.retain(|url| seen.insert(url.to_string()));
+    urls
 }
 
-impl YoutubeSubscribtions {
+// the channel of a fetched feed, its url when it has no videos
+fn fetched_name(url: &str, fetched: &Fetched) -> String {
+    match fetched {
+        Fetched::Videos(videos, _) if !videos.is_empty() => videos[0].channel.to_string(),
+        _ => url.to_string(),
+    }
+}
 
-    fn clear_and_print_videos(&mut self) {
-        clear();
-        move_cursor(0);
-        print_videos(&self.toshow)
+/// called with the number of fetched feeds, the number of feeds and the name of the last one
+type Progress<'a> = &'a (dyn Fn(usize, usize, &str) + Sync);
+
+fn no_progress(_done: usize, _total: usize, _name: &str) {
+}
+
+async fn get_videos(urls: &[String], spread: bool, validators: &HashMap<String, Validator>, progress: Progress<'_>, app_config: &AppConfig) -> Vec<(String, Fetched)> {
+    let done = &AtomicUsize::new(0);
+    let fetch = move |url: String| async move {
+        let fetched = fetch_feed_videos(&url, validators.get(&url), app_config).await;
+        progress(done.fetch_add(1, Ordering::SeqCst) + 1, urls.len(), &fetched_name(&url, &fetched));
+        (url, fetched)
+    };
+    if spread && app_config.refresh_spread_seconds > 0 {
+        return get_videos_spread(urls, fetch, app_config).await
     }
+    stream::iter(urls.iter().cloned()).map(fetch).buffered(CONCURRENT_REQUESTS).collect().await
+}
 
-    fn move_page(&mut self, direction: i8) {
-        self.n = get_lines();
-        if direction == 1 {
-            if self.start + 2 * self.n < self.videos.videos.len() {
-                self.start += self.n;
+// feeds not fetched for refetch_after_minutes
+fn get_stale_feeds(urls: &[String], fetched: &HashMap<String, i64>, app_config: &AppConfig) -> Vec<String> {
+    let now = now_timestamp();
+    let threshold = app_config.refetch_after_minutes as i64 * 60;
+    urls.iter().filter( |url|
+        fetched.get(*url).map(|fetched| now - fetched >= threshold).unwrap_or(true)
+    ).cloned().collect::<Vec<String>>()
+}
+
+fn remove_unsubscribed(videos: &mut Videos, urls: &[String]) {
+    let subscribed = urls.iter().collect::<HashSet<&String>>();
+    videos.videos.retain(|video| subscribed.contains(&video.feed));
+    videos.fetched.retain(|url, _| subscribed.contains(url));
+    videos.validators.retain(|url, _| subscribed.contains(url));
+}
+
+// youtube videos are identified by id as the same video can be in several feeds (channel and playlist)
+fn video_key(video: &Video) -> String {
+    match get_id(video) {
+        Some(Some(id)) if is_youtube(video) => id,
+        _ => video.url.to_string(),
+    }
+}
+
+// keeps the first of the videos with the same key
+fn dedup_videos(videos: &mut Vec<Video>) {
+    let mut seen = HashSet::new();
+    videos.retain(|video| seen.insert(video_key(video)));
+}
+
+// videos of a fetched feed which are not cached yet, none for feeds never fetched before
+fn get_new_videos(videos: &Videos, url: &str, result: &Fetched) -> Vec<Video> {
+    match result {
+        Fetched::Videos(fresh, _) if videos.fetched.contains_key(url) => {
+            let known = videos.videos.iter().map(video_key).collect::<HashSet<String>>();
+            fresh.iter().filter(|video| !known.contains(&video_key(video))).cloned().collect()
+        },
+        _ => vec![],
+    }
+}
+
+// fetched videos are added to the cached ones (feeds only list the last ones),
+// cached videos older than retention_days are removed, returns the new videos
+// a video listed by several feeds stays with the feed it was first cached from
+fn merge_fetched(videos: &mut Videos, url: String, result: Fetched, app_config: &AppConfig) -> Vec<Video> {
+    let new = get_new_videos(videos, &url, &result);
+    match result {
+        Fetched::Videos(mut fresh, validator) => {
+            dedup_videos(&mut videos.videos);
+            // durations and statistics missing from the feeds are kept from the fetched ones
+            let known = videos.videos.iter()
+                .map(|video| (video_key(video), video.clone()))
+                .collect::<HashMap<String, Video>>();
+            fresh.retain(|video| known.get(&video_key(video)).map(|known| known.feed == url).unwrap_or(true));
+            let now = now_timestamp();
+            for video in fresh.iter_mut() {
+                let known = known.get(&video_key(video));
+                video.added = known.and_then(|known| known.added).or(Some(now));
+                if let Some(known) = known {
+                    video.duration = video.duration.or(known.duration);
+                    video.views = video.views.or(known.views);
+                    video.likes = video.likes.or(known.likes);
+                    video.subscribers = video.subscribers.take().or(known.subscribers.clone());
+                    video.premiere = video.premiere.or(known.premiere);
+                }
             }
+            let fresh_keys = fresh.iter().map(video_key).collect::<HashSet<String>>();
+            let retention = app_config.retention_days as i64 * 86400;
+            videos.videos.retain( |video|
+                video.feed != url || (!fresh_keys.contains(&video_key(video))
+                    && (retention == 0 || parse_timestamp(&video.published).map(|t| now - t <= retention).unwrap_or(true))));
+            videos.videos.extend(fresh);
+            dedup_videos(&mut videos.videos);
+            videos.validators.insert(url.to_string(), validator);
+            videos.fetched.insert(url, now_timestamp());
+        },
+        Fetched::NotModified => {
+            videos.fetched.insert(url, now_timestamp());
+        },
+        Fetched::Failed => (),
+    }
+    new
+}
+
+fn notify_new_videos(new: &[Video], app_config: &AppConfig) {
+    if !app_config.notifications || app_config.notify_command.is_empty() {
+        return
+    }
+    let new = new.iter()
+        .filter(|video| !app_config.notify_pinned_only || in_channel_group(video, &app_config.pinned_channels))
+        .collect::<Vec<&Video>>();
+    let messages = if new.len() > 5 {
+        vec![(format!("{} new videos", new.len()), new.iter().map(|video| video.channel.to_string()).collect::<Vec<String>>().join(", "))]
+    } else {
+        new.iter().map(|video| (video.channel.to_string(), video.title.to_string())).collect()
+    };
+    for (summary, body) in messages {
+        let _res = Command::new(&app_config.notify_command[0])
+            .args(&app_config.notify_command[1..])
+            .arg(summary)
+            .arg(body)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+}
+
+fn run_new_video_hook(new: &Vec<Video>, app_config: &AppConfig) {
+    if app_config.on_new_video.is_empty() {
+        return
+    }
+    for video in new {
+        let _res = Command::new(&app_config.on_new_video[0])
+            .args(&app_config.on_new_video[1..])
+            .arg(&video.title)
+            .arg(&video.channel)
+            .arg(watch_url(video).unwrap_or(video.url.to_string()))
+            .env("YTS_TITLE", &video.title)
+            .env("YTS_CHANNEL", &video.channel)
+            .env("YTS_URL", watch_url(video).unwrap_or(video.url.to_string()))
+            .env("YTS_PUBLISHED", &video.published)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+}
+
+// called with the videos discovered by a refresh
+fn announce_new_videos(new: &Vec<Video>, app_config: &AppConfig) {
+    notify_new_videos(new, app_config);
+    run_new_video_hook(new, app_config);
+}
+
+// only fetches the stale feeds, the cached videos of the other feeds are kept
+async fn get_videos_incremental(urls: &[String], cached: Videos, spread: bool, progress: Progress<'_>, app_config: &AppConfig) -> Videos {
+    let mut videos = cached;
+    remove_unsubscribed(&mut videos, urls);
+    let stale = get_stale_feeds(urls, &videos.fetched, app_config);
+    let mut new = vec![];
+    for (url, result) in get_videos(&stale, spread, &videos.validators, progress, app_config).await {
+        new.extend(merge_fetched(&mut videos, url, result, app_config));
+    }
+    announce_new_videos(&new, app_config);
+    videos
+}
+
+type TextMatcher = Box<dyn Fn(&str) -> bool>;
+
+// `re:` prefixed patterns are regular expressions, an invalid one matches nothing
+fn text_matcher(pattern: &str, case_sensitive: bool) -> TextMatcher {
+    match pattern.strip_prefix("re:") {
+        Some(re) => match regex::RegexBuilder::new(re).case_insensitive(!case_sensitive).build() {
+            Ok(re) => Box::new(move |text| re.is_match(text)),
+            Err(_) => Box::new(|_| false),
+        },
+        None if case_sensitive => {
+            let pattern = pattern.to_string();
+            Box::new(move |text| text.contains(pattern.as_str()))
+        },
+        None => {
+            let pattern = pattern.to_lowercase();
+            Box::new(move |text| text.to_lowercase().contains(pattern.as_str()))
         }
-        else if direction == 0 {
-            self.start = 0;
+    }
+}
+
+// text searched by `/` and `f`
+fn matches_text(video: &Video, matcher: &TextMatcher) -> bool {
+    matcher(&video.title) || matcher(&video.channel) || matcher(&video.description)
+}
+
+// `!` or `-` prefixed filters hide the matching videos
+fn negated_filter(filter: &str) -> (bool, &str) {
+    match filter.strip_prefix('!').or(filter.strip_prefix('-')) {
+        Some(rest) => (true, rest),
+        None => (false, filter),
+    }
+}
+
+fn is_short(video: &Video) -> bool {
+    video.short || video.url.contains("/shorts/")
+        || (is_youtube(video) && video.duration.map(|duration| duration > 0 && duration <= 60).unwrap_or(false))
+}
+
+// `>20m`, `<5m`, `>1h` or `<90s` filters on the duration, None for other filters
+fn matches_duration(video: &Video, filter: &str) -> Option<bool> {
+    let (longer, limit) = match filter.chars().next() {
+        Some('>') => (true, &filter[1..]),
+        Some('<') => (false, &filter[1..]),
+        _ => return None,
+    };
+    let seconds = match limit.char_indices().last() {
+        Some((i, 'h')) => limit[..i].parse::<u64>().ok()? * 3600,
+        Some((i, 'm')) => limit[..i].parse::<u64>().ok()? * 60,
+        Some((i, 's')) => limit[..i].parse::<u64>().ok()?,
+        _ => limit.parse::<u64>().ok()? * 60,
+    };
+    Some(video.duration.map(|duration| if longer { duration > seconds } else { duration < seconds }).unwrap_or(false))
+}
+
+// `[LIVE]` or `[PREMIERE in 2h]` shown before the title
+fn video_label(video: &Video, now: i64) -> String {
+    match video.premiere {
+        _ if video.live => "[LIVE] ".to_string(),
+        Some(premiere) if premiere > now => format!("[PREMIERE in {}] ", format_age(premiere - now)),
+        _ => "".to_string(),
+    }
+}
+
+// urls of the newest videos of each channel, at most max of them
+fn newest_per_channel(videos: &[Video], max: usize) -> HashSet<String> {
+    let mut sorted = videos.iter().collect::<Vec<&Video>>();
+    sorted.sort_by(|a, b| b.published.cmp(&a.published));
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    sorted.into_iter().filter(|video| {
+        let count = counts.entry(video.channel.as_str()).or_insert(0);
+        *count += 1;
+        *count <= max
+    }).map(|video| video.url.to_string()).collect()
+}
+
+// the channel of `channel:` filters
+fn in_channel(video: &Video, channel: &str, case_sensitive: bool) -> bool {
+    if case_sensitive {
+        video.channel == channel
+    } else {
+        video.channel.to_lowercase() == channel.to_lowercase()
+    }
+}
+
+fn filter_videos(videos: &[Video], filter: &str, case_sensitive: bool) -> Vec<Video> {
+    let (negated, filter) = negated_filter(filter);
+    let matcher = text_matcher(filter, case_sensitive);
+    videos.iter().filter(|video| negated !=
+        match filter.strip_prefix("channel:") {
+            Some(channel) => in_channel(video, channel, case_sensitive),
+            None => matches_duration(video, filter).unwrap_or_else(|| matches_text(video, &matcher)),
         }
-        else if direction == -1 {
-            if self.n > self.start {
-                self.start = 0;
+    ).cloned().collect::<Vec<Video>>()
+}
+
+struct ChannelSummary {
+    name: String,
+    count: usize,
+    unread: usize,
+    last_upload: String,
+}
+
+fn get_channel_summaries(videos: &Vec<Video>, state: &State) -> Vec<ChannelSummary> {
+    let mut channels: Vec<ChannelSummary> = vec![];
+    for video in videos {
+        let unread = match get_id(video) {
+            Some(Some(id)) if state.watched.contains(&id) => 0,
+            _ => 1,
+        };
+        match channels.iter_mut().find(|c| c.name == video.channel) {
+            Some(channel) => {
+                channel.count += 1;
+                channel.unread += unread;
+                if video.published > channel.last_upload {
+                    channel.last_upload = video.published.to_string();
+                }
+            },
+            None => channels.push(ChannelSummary {
+                name: video.channel.to_string(),
+                count: 1,
+                unread,
+                last_upload: video.published.to_string(),
+            }),
+        }
+    }
+    channels.sort_by(|a, b| b.last_upload.cmp(&a.last_upload));
+    channels
+}
+
+fn in_channel_group(video: &Video, group: &[String]) -> bool {
+    group.iter().any(|entry| video.channel == *entry || (!video.feed.is_empty() && video.feed.ends_with(entry.as_str())))
+}
+
+const SORT_ORDERS: &[&str] = &["date", "channel", "title", "duration", "added"];
+
+// videos are sorted by publication date after sort_order
+fn compare_videos(a: &Video, b: &Video, sort_order: &str) -> std::cmp::Ordering {
+    match sort_order {
+        "channel" => a.channel.to_lowercase().cmp(&b.channel.to_lowercase()),
+        "title" => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+        "duration" => b.duration.cmp(&a.duration),
+        "added" => b.added.cmp(&a.added),
+        _ => std::cmp::Ordering::Equal,
+    }.then(b.published.cmp(&a.published))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn to_show_videos(videos: &mut [Video], start: usize, end: usize, filter: &str, case_sensitive: bool, sort_order: &str, keep: &dyn Fn(&Video) -> bool, first: &dyn Fn(&Video) -> bool) -> (Vec<Video>, Vec<Video>) {
+    videos.sort_by(|a, b| first(b).cmp(&first(a)).then(compare_videos(a, b, sort_order)));
+    let filtered_videos = filter_videos(videos, filter, case_sensitive).into_iter().filter(|video| keep(video)).collect::<Vec<Video>>();
+    let new_end = std::cmp::min(end, filtered_videos.len());
+    let mut result = filtered_videos[min(start, new_end)..new_end].to_vec();
+    result.reverse();
+    (result, filtered_videos)
+}
+
+async fn get_subscribed_urls(app_config: &AppConfig) -> Option<Vec<String>> {
+    let oauth_token = load_oauth_token();
+    let xml = match oauth_token {
+        Some(_) => get_subscriptions_xml().or(Ok("<opml/>".to_string())),
+        None => get_subscriptions_xml(),
+    };
+    match xml {
+        Ok(xml) => {
+            let mut channel_ids = app_config.channel_ids.clone();
+            if let Some(token) = &oauth_token {
+                channel_ids.extend(get_oauth_channel_ids(token, app_config).await);
             }
-            else {
-                self.start = self.start - self.n;
+            Some(get_feed_urls(xml.as_str(), &channel_ids, app_config))
+        },
+        Err(_) => None,
+    }
+}
+
+fn load(reload: bool, spread: bool, progress: Progress, app_config: &AppConfig) -> Option<Videos> {
+    let path = app_config.cache_path.as_str();
+    // a cache which can't be read even after migration is fetched again rather than failing
+    let cached = read_versioned(path, CACHE_MIGRATIONS, app_config.read_only)
+        .and_then(|json| serde_json::from_value::<Videos>(json).ok());
+    if !reload {
+        if let Some(videos) = cached {
+            return Some(videos)
+        }
+    }
+    match runtime().block_on(get_subscribed_urls(app_config)) {
+        Some(urls) => {
+            let mut videos = runtime().block_on(get_videos_incremental(&urls, cached.unwrap_or_default(), spread, progress, app_config));
+            if app_config.fetch_durations {
+                let durations = runtime().block_on(get_durations(&missing_durations(&videos), app_config));
+                set_durations(&mut videos, durations);
+            }
+            save_videos(&mut videos, app_config);
+            Some(videos)
+        },
+        None => None,
+    }
+}
+
+// feeds fetched more recently by another instance are taken from the cache on disk
+fn merge_newer_feeds(videos: &mut Videos, on_disk: Videos) {
+    for (url, fetched) in on_disk.fetched {
+        if videos.fetched.get(&url).map(|&ours| ours < fetched).unwrap_or(false) {
+            videos.videos.retain(|video| video.feed != url);
+            videos.videos.extend(on_disk.videos.iter().filter(|video| video.feed == url).cloned());
+            dedup_videos(&mut videos.videos);
+            if let Some(validator) = on_disk.validators.get(&url) {
+                videos.validators.insert(url.to_string(), validator.clone());
+            }
+            videos.fetched.insert(url, fetched);
+        }
+    }
+}
+
+fn save_videos(videos: &mut Videos, app_config: &AppConfig) {
+    if app_config.read_only {
+        return
+    }
+    let _lock = lock_file(&app_config.cache_path);
+    if let Some(on_disk) = read_versioned(&app_config.cache_path, CACHE_MIGRATIONS, true)
+        .and_then(|json| serde_json::from_value::<Videos>(json).ok()) {
+        merge_newer_feeds(videos, on_disk);
+    }
+    let serialized = serde_json::to_string(&to_versioned_json(videos, CACHE_MIGRATIONS)).unwrap();
+    write_compressed(&app_config.cache_path, &serialized, configured_compression(app_config)).expect("writing videos json failed");
+}
+
+fn extract_channel_id(page: &str) -> Option<String> {
+    ["\"externalId\":\"", "\"channelId\":\"", "youtube.com/channel/"].iter().flat_map( |marker|
+        page.split(marker).nth(1).map( |rest|
+            rest.chars().take_while(|c| c.is_alphanumeric() || *c == '-' || *c == '_').collect::<String>())
+    ).find(|id| id.starts_with("UC") && id.len() == 24)
+}
+
+fn is_video_url(url: &str) -> bool {
+    url.contains("youtube.com/watch") || url.contains("youtu.be/") || url.contains("youtube.com/shorts/")
+}
+
+async fn get_video_author_url(video_url: &str, app_config: &AppConfig) -> Option<String> {
+    let request = http_get("https://www.youtube.com/oembed", app_config).query(&[("url", video_url), ("format", "json")]);
+    call_json(request, app_config).await
+        .and_then(|json| json["author_url"].as_str().map(|s| s.to_string()))
+}
+
+async fn resolve_channel_id(input: &str, app_config: &AppConfig) -> Option<String> {
+    let input = input.trim();
+    if input.starts_with("UC") && input.len() == 24 {
+        return Some(input.to_string())
+    }
+    if is_video_url(input) {
+        let author_url = get_video_author_url(input, app_config).await?;
+        return Box::pin(resolve_channel_id(&author_url, app_config)).await
+    }
+    if let Some(id) = input.split("/channel/").nth(1).and_then(|rest| rest.split(['/', '?']).next()) {
+        return Some(id.to_string())
+    }
+    let url = if input.starts_with("@") {
+        format!("https://www.youtube.com/{}", input)
+    } else if input.starts_with("http") {
+        input.to_string()
+    } else {
+        format!("https://{}", input)
+    };
+    call_text(http_get(url.as_str(), app_config), app_config).await.and_then(|page| extract_channel_id(page.as_str()))
+}
+
+fn get_opml_outlines(xml: &str) -> Vec<(String, String)> {
+    match parser::parse(xml) {
+        Ok(package) => match evaluate_xpath(&package.as_document(), "//outline[@xmlUrl]") {
+            Ok(Value::Nodeset(outlines)) => outlines.document_order().iter().flat_map( |outline|
+                outline.element().map( |element| (
+                    element.attribute_value("title").or(element.attribute_value("text")).unwrap_or("").to_string(),
+                    element.attribute_value("xmlUrl").unwrap_or("").to_string(),
+                ))
+            ).collect(),
+            _ => vec![],
+        },
+        Err(_) => vec![],
+    }
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace("&", "&amp;").replace("<", "&lt;").replace(">", "&gt;").replace("\"", "&quot;")
+}
+
+fn to_opml(outlines: &[(String, String)]) -> String {
+    let body = outlines.iter().map( |(title, url)|
+        format!("<outline text=\"{}\" title=\"{}\" type=\"rss\" xmlUrl=\"{}\" />", xml_escape(title), xml_escape(title), xml_escape(url))
+    ).collect::<Vec<String>>().join("");
+    format!("<opml version=\"1.1\"><body><outline text=\"YouTube Subscriptions\" title=\"YouTube Subscriptions\">{}</outline></body></opml>", body)
+}
+
+fn confirm(question: &str) -> bool {
+    print!("{} [y/N] ", question);
+    io::stdout().flush().unwrap();
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).unwrap();
+    answer.trim() == "y"
+}
+
+fn import_opml(import_path: &String, app_config: &AppConfig) {
+    if app_config.read_only {
+        println!("subscriptions are read-only");
+        std::process::exit(1)
+    }
+    let path = subscriptions_path().expect("failed to find home directory");
+    let imported = get_opml_outlines(fs::read_to_string(import_path).expect("failed reading imported file").as_str());
+    let current = get_opml_outlines(fs::read_to_string(&path).unwrap_or("<opml/>".to_string()).as_str());
+    let added = imported.iter().filter(|(_, url)| !current.iter().any(|(_, u)| u == url)).collect::<Vec<&(String, String)>>();
+    let removed = current.iter().filter(|(_, url)| !imported.iter().any(|(_, u)| u == url)).collect::<Vec<&(String, String)>>();
+    if added.is_empty() && removed.is_empty() {
+        println!("no changes in subscriptions");
+        return
+    }
+    println!("{} channels added, {} channels removed", added.len(), removed.len());
+    let mut result = current.clone();
+    for (title, url) in &added {
+        if confirm(&format!("{} add ?", colorize("32", &format!("+ {}", title)))) {
+            result.push((title.to_string(), url.to_string()));
+        }
+    }
+    for (title, url) in &removed {
+        if confirm(&format!("{} remove ?", colorize("31", &format!("- {}", title)))) {
+            result.retain(|(_, u)| u != url);
+        }
+    }
+    if let Err(e) = write_atomically(&path, to_opml(&result).as_bytes()) {
+        println!("failed writing {}: {}", path, e);
+        std::process::exit(1)
+    }
+    println!("{} now has {} channels", path, result.len());
+}
+
+fn preview_and_add_channel(input: &str, app_config: &AppConfig) {
+    if app_config.read_only {
+        println!("subscriptions are read-only");
+        std::process::exit(1)
+    }
+    let channel_id = match runtime().block_on(resolve_channel_id(input, app_config)) {
+        Some(id) => id,
+        None => {
+            println!("could not find a channel for {}", input);
+            return
+        }
+    };
+    let channel_id = channel_id.as_str();
+    let url = format!("https://www.youtube.com/feeds/videos.xml?channel_id={}", channel_id);
+    let mut videos = runtime().block_on(get_channel_videos(url, app_config));
+    if videos.is_empty() {
+        println!("no videos found for channel {}", channel_id);
+        return
+    }
+    videos.sort_by(|a, b| b.published.cmp(&a.published));
+    println!("last uploads from {}:", videos[0].channel);
+    println!();
+    for video in videos.iter().take(15) {
+        println!("  {} {}", colorize("36", video.published.split("T").next().unwrap_or("")), video.title);
+    }
+    println!();
+    if confirm(&format!("subscribe to {} ?", videos[0].channel)) {
+        match add_channel_id_to_config(channel_id) {
+            Ok(_) => println!("subscribed to {}", videos[0].channel),
+            Err(e) => println!("failed to update configuration: {}", e),
+        }
+    }
+}
+
+// the migrated cache, which has a videos list
+fn read_cache_json(app_config: &AppConfig) -> Result<serde_json::Value, String> {
+    let (contents, _) = read_compressed(&app_config.cache_path)
+        .map_err(|e| format!("failed reading {}: {}", app_config.cache_path, e))?;
+    let mut json = serde_json::from_str::<serde_json::Value>(contents.as_str())
+        .map_err(|e| format!("{} is not valid json: {}", app_config.cache_path, e))?;
+    let version = json["version"].as_u64().unwrap_or(0);
+    if migrate(&mut json, CACHE_MIGRATIONS) {
+        println!("cache schema is at version {}, current version is {}", version, CACHE_MIGRATIONS.len());
+    }
+    if !json["videos"].is_array() {
+        return Err(format!("{} has no videos list", app_config.cache_path))
+    }
+    Ok(json)
+}
+
+fn repair_cache_entry(entry: &serde_json::Value) -> Option<Video> {
+    let field = |name: &str| entry[name].as_str().unwrap_or("").to_string();
+    entry["url"].as_str()?;
+    Some(Video {
+        channel: field("channel"),
+        title: field("title"),
+        thumbnail: field("thumbnail"),
+        url: field("url"),
+        published: field("published"),
+        description: field("description"),
+        feed: field("feed"),
+        short: entry["short"].as_bool().unwrap_or(false),
+        duration: entry["duration"].as_u64(),
+        views: entry["views"].as_u64(),
+        likes: entry["likes"].as_u64(),
+        subscribers: entry["subscribers"].as_str().map(|s| s.to_string()),
+        live: entry["live"].as_bool().unwrap_or(false),
+        premiere: entry["premiere"].as_i64(),
+        added: entry["added"].as_i64(),
+    })
+}
+
+// exits with 1 when the cache cannot be updated, 2 when some feeds failed
+fn refresh_command(app_config: &AppConfig) {
+    if app_config.read_only {
+        println!("cache is read-only");
+        std::process::exit(1)
+    }
+    let urls = match runtime().block_on(get_subscribed_urls(app_config)) {
+        Some(urls) => urls,
+        None => {
+            println!("could not read subscriptions");
+            std::process::exit(1)
+        }
+    };
+    let mut videos = read_versioned(&app_config.cache_path, CACHE_MIGRATIONS, false)
+        .and_then(|json| serde_json::from_value::<Videos>(json).ok())
+        .unwrap_or_default();
+    remove_unsubscribed(&mut videos, &urls);
+    let stale = get_stale_feeds(&urls, &videos.fetched, app_config);
+    let mut failed = vec![];
+    let mut new = vec![];
+    for (url, result) in runtime().block_on(get_videos(&stale, true, &videos.validators, &no_progress, app_config)) {
+        if let Fetched::Failed = result {
+            failed.push(url.to_string());
+        }
+        new.extend(merge_fetched(&mut videos, url, result, app_config));
+    }
+    if app_config.fetch_durations {
+        let durations = runtime().block_on(get_durations(&missing_durations(&videos), app_config));
+        set_durations(&mut videos, durations);
+    }
+    save_videos(&mut videos, app_config);
+    announce_new_videos(&new, app_config);
+    println!("{} feeds fetched, {} failed", stale.len() - failed.len(), failed.len());
+    for url in &failed {
+        println!("failed: {}", url);
+    }
+    if !failed.is_empty() {
+        std::process::exit(2)
+    }
+}
+
+fn cache_command(command: &str, app_config: &AppConfig) {
+    // held from the read to the write of a repair, so that a concurrent refresh is not lost
+    let _lock = if command == "repair" { lock_file(&app_config.cache_path) } else { None };
+    let mut json = match read_cache_json(app_config) {
+        Ok(json) => json,
+        Err(e) => {
+            println!("{}", e);
+            std::process::exit(1)
+        }
+    };
+    let entries = json["videos"].as_array().cloned().unwrap_or_default();
+    let videos = entries.iter().flat_map(|entry| serde_json::from_value::<Video>(entry.clone()).ok()).collect::<Vec<Video>>();
+    let invalid = entries.len() - videos.len();
+    match command {
+        "stats" => {
+            let mut channels: Vec<(String, usize)> = vec![];
+            for video in &videos {
+                match channels.iter_mut().find(|(channel, _)| *channel == video.channel) {
+                    Some((_, count)) => *count += 1,
+                    None => channels.push((video.channel.to_string(), 1)),
+                }
+            }
+            channels.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+            for (channel, count) in &channels {
+                println!("{:>6} {}", count, channel);
+            }
+            println!("{} videos from {} channels, {} invalid entries", videos.len(), channels.len(), invalid);
+        },
+        "verify" => {
+            if invalid > 0 {
+                println!("{} invalid entries out of {}, run `cache repair` to remove them", invalid, entries.len());
+                std::process::exit(1)
+            }
+            println!("{} entries, all valid", entries.len());
+        },
+        "repair" => {
+            if app_config.read_only {
+                println!("cache is read-only");
+                std::process::exit(1)
+            }
+            let repaired = entries.iter().flat_map(repair_cache_entry).collect::<Vec<Video>>();
+            println!("repaired {} entries, removed {}", repaired.len() - videos.len(), entries.len() - repaired.len());
+            json["videos"] = serde_json::to_value(&repaired).unwrap();
+            let serialized = serde_json::to_string(&json).unwrap();
+            write_compressed(&app_config.cache_path, &serialized, configured_compression(app_config)).expect("writing videos json failed");
+        },
+        "export" => println!("{}", serde_json::to_string_pretty(&to_versioned_json(&Videos { videos, ..Default::default() }, CACHE_MIGRATIONS)).unwrap()),
+        _ => {
+            println!("unknown cache command {}, expected one of stats, verify, repair, export", command);
+            std::process::exit(1)
+        }
+    }
+}
+
+fn get_lines() -> usize {
+    match terminal::size() {
+        Ok((_, h)) => std::cmp::max(h.saturating_sub(1), 1) as usize,
+        Err(_) => 20,
+    }
+}
+
+fn get_cols() -> usize {
+    match terminal::size() {
+        Ok((w, _)) => w as usize,
+        Err(_) => 20,
+    }
+}
+
+// set on resize events, the main loop then reflows the list
+static RESIZED: AtomicBool = AtomicBool::new(false);
+
+// set by the SIGINT and SIGTERM handler, key reads then return None so that every screen
+// goes back to the main loop, which quits like with q
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_interrupt(_signal: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+fn watch_signals() {
+    unsafe {
+        libc::signal(libc::SIGINT, on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t);
+        libc::signal(libc::SIGTERM, on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t);
+    }
+}
+
+fn modified_time(path: &Option<String>) -> Option<SystemTime> {
+    path.as_ref().and_then(|p| fs::metadata(p).ok()).and_then(|m| m.modified().ok())
+}
+
+fn hide_cursor() {
+    let _ = execute!(io::stdout(), cursor::Hide);
+}
+
+fn smcup() {
+    let _ = execute!(io::stdout(), terminal::EnterAlternateScreen);
+}
+
+fn rmcup() {
+    let _ = execute!(io::stdout(), terminal::LeaveAlternateScreen);
+}
+
+// saves the terminal title on the title stack of xterm compatible terminals
+fn push_title() {
+    print!("\x1b[22;0t");
+    io::stdout().flush().unwrap();
+}
+
+fn pop_title() {
+    print!("\x1b[23;0t");
+    io::stdout().flush().unwrap();
+}
+
+fn set_title(title: &str) {
+    let _ = execute!(io::stdout(), terminal::SetTitle(title.replace(|c: char| c.is_control(), "")));
+}
+
+// pushes the visible lines into the terminal scrollback instead of erasing them
+fn scroll_to_scrollback() {
+    move_to_bottom();
+    print!("{}", "\n".repeat(get_lines() + 1));
+    move_cursor(0);
+}
+
+fn clear() {
+    let _ = execute!(io::stdout(), terminal::Clear(terminal::ClearType::All));
+}
+
+fn show_cursor() {
+    let _ = execute!(io::stdout(), cursor::Show);
+}
+
+fn move_cursor(i: usize) {
+    let _ = execute!(io::stdout(), cursor::MoveTo(0, i as u16));
+}
+
+fn move_cursor_to(row: usize, col: usize) {
+    let _ = queue!(io::stdout(), cursor::MoveTo(col as u16, row as u16));
+}
+
+fn move_to_bottom() {
+    let _ = execute!(io::stdout(), cursor::MoveTo(0, get_lines() as u16));
+}
+
+fn clear_to_end_of_line() {
+    let _ = execute!(io::stdout(), terminal::Clear(terminal::ClearType::UntilNewLine));
+}
+
+thread_local! {
+    // last message of debug, shown on the status bar until the next key press
+    static MESSAGE: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+fn debug(s: &String) {
+    MESSAGE.with(|message| *message.borrow_mut() = s.to_string());
+    move_to_bottom();
+    clear_to_end_of_line();
+    move_to_bottom();
+    print!("{}", s);
+    io::stdout().flush().unwrap();
+}
+
+/// SGR parameters (e.g. "1;34") used for each element of the UI, empty means no style
+#[derive(Debug, Clone)]
+struct Theme {
+    date: String,
+    pinned: String,
+    channel: String,
+    watched: String,
+    selector: String,
+    highlight: String,
+    priority: String,
+    stale: String,
+    new: String,
+}
+
+fn theme_preset(name: &str) -> Theme {
+    let colors = match name {
+        "high-contrast" => ["1;96", "1;93", "1;97", "37", "1;93", "1;93", "1;95", "90", "1;92"],
+        "light-terminal" => ["35", "31", "32", "2", "1", "31", "1;31", "37", "1;32"],
+        "monochrome" => ["", "1", "", "2", "1", "1", "1;4", "2", "1"],
+        _ => ["36", "33", "34", "2", "1", "33", "1;35", "2", "32"],
+    };
+    Theme {
+        date: colors[0].to_string(),
+        pinned: colors[1].to_string(),
+        channel: colors[2].to_string(),
+        watched: colors[3].to_string(),
+        selector: colors[4].to_string(),
+        highlight: colors[5].to_string(),
+        priority: colors[6].to_string(),
+        stale: colors[7].to_string(),
+        new: colors[8].to_string(),
+    }
+}
+
+fn get_theme(app_config: &AppConfig) -> Theme {
+    let mut theme = theme_preset(&app_config.theme);
+    for (element, color) in &app_config.theme_colors {
+        match element.as_str() {
+            "date" => theme.date = color.to_string(),
+            "pinned" => theme.pinned = color.to_string(),
+            "channel" => theme.channel = color.to_string(),
+            "watched" => theme.watched = color.to_string(),
+            "selector" => theme.selector = color.to_string(),
+            "highlight" => theme.highlight = color.to_string(),
+            "priority" => theme.priority = color.to_string(),
+            "stale" => theme.stale = color.to_string(),
+            "new" => theme.new = color.to_string(),
+            _ => (),
+        }
+    }
+    theme
+}
+
+// https://no-color.org, also set by --no-color and no_color
+fn no_color() -> bool {
+    env::var_os("NO_COLOR").map(|value| !value.is_empty()).unwrap_or(false)
+}
+
+// resets the colors of a line which may have been truncated in the middle of a colored text
+fn reset() -> &'static str {
+    if no_color() { "" } else { "\x1b[0m" }
+}
+
+fn colorize(color: &str, s: &str) -> String {
+    if color.is_empty() || no_color() {
+        s.to_string()
+    } else {
+        format!("\x1b[{}m{}\x1b[0m", color, s)
+    }
+}
+
+// OSC 8 hyperlink, opened with ctrl-click (or click) on the terminals supporting it
+fn hyperlink(url: &str, text: &str) -> String {
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+}
+
+// start and end of the first http(s) url of a text, without the punctuation following it
+fn find_url(text: &str) -> Option<(usize, usize)> {
+    let start = text.find("http://").into_iter().chain(text.find("https://")).min()?;
+    let end = text[start..].find(|c: char| c.is_whitespace() || c == '"' || c == '<' || c == '>').map(|end| start + end).unwrap_or(text.len());
+    let url = text[start..end].trim_end_matches(&['.', ',', ';', ':', ')', '!', '?'][..]);
+    Some((start, start + url.len()))
+}
+
+// makes the http(s) urls of a text clickable
+fn link_urls(text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some((start, end)) = find_url(rest) {
+        result.push_str(&rest[..start]);
+        result.push_str(&hyperlink(&rest[start..end], &rest[start..end]));
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+    result
+}
+
+fn extract_urls(text: &str) -> Vec<String> {
+    let mut urls: Vec<String> = vec![];
+    let mut rest = text;
+    while let Some((start, end)) = find_url(rest) {
+        if !rest[start..end].ends_with("://") && !urls.iter().any(|url| url == &rest[start..end]) {
+            urls.push(rest[start..end].to_string());
+        }
+        rest = &rest[end..];
+    }
+    urls
+}
+
+// through the terminal (OSC 52), which also works over ssh
+fn copy_to_clipboard(text: &str) {
+    print!("\x1b]52;c;{}\x07", base64(text.as_bytes()));
+    io::stdout().flush().unwrap();
+}
+
+/// truncates s to cols visible characters, escape sequences are kept but not counted
+fn truncate_visible(s: &str, cols: usize) -> String {
+    let mut result = String::new();
+    let mut visible = 0;
+    let mut in_escape = false;
+    // OSC sequences (hyperlinks) end with BEL or ESC \ instead of a letter
+    let mut in_osc = false;
+    let mut previous = ' ';
+    for c in s.chars() {
+        if in_osc {
+            in_osc = !(c == '\x07' || (previous == '\x1b' && c == '\\'));
+        } else if c == '\x1b' {
+            in_escape = true;
+        } else if in_escape && previous == '\x1b' && c == ']' {
+            in_escape = false;
+            in_osc = true;
+        } else if in_escape {
+            in_escape = !c.is_ascii_alphabetic();
+        } else {
+            if visible == cols {
+                if s.contains("\x1b]8;") {
+                    result.push_str("\x1b]8;;\x1b\\");
+                }
+                break;
+            }
+            visible += 1;
+        }
+        result.push(c);
+        previous = c;
+    }
+    result
+}
+
+thread_local! {
+    // screen row of each video of the list, date headers take rows in between
+    static VIDEO_ROWS: RefCell<Vec<usize>> = const { RefCell::new(vec![]) };
+    // first column of the list, after the channel sidebar
+    static LIST_LEFT: Cell<usize> = const { Cell::new(0) };
+}
+
+fn video_row(i: usize) -> usize {
+    VIDEO_ROWS.with(|rows| rows.borrow().get(i).cloned().unwrap_or(i))
+}
+
+fn list_left() -> usize {
+    LIST_LEFT.with(|left| left.get())
+}
+
+fn print_selector(i: usize, col: usize, theme: &Theme) {
+    move_cursor_to(i, col);
+    print!("{}", colorize(&theme.selector, "|"));
+    io::stdout().flush().unwrap();
+}
+
+fn clear_selector(i: usize, col: usize) {
+    move_cursor_to(i, col);
+    print!(" ");
+    io::stdout().flush().unwrap();
+}
+
+fn jump(i: usize, new_i: usize) -> usize {
+    clear_selector(video_row(i), list_left());
+    new_i
+}
+
+// the keys the program handles, Ctrl is a letter typed with control
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Key {
+    Char(char),
+    Ctrl(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Backspace,
+    Esc,
+}
+
+fn to_key(event: event::KeyEvent) -> Option<Key> {
+    if event.kind == KeyEventKind::Release {
+        return None
+    }
+    Some(match event.code {
+        KeyCode::Char(c) if event.modifiers.contains(KeyModifiers::CONTROL) => Key::Ctrl(c),
+        KeyCode::Char(c) => Char(c),
+        KeyCode::Enter => Char('\n'),
+        KeyCode::Tab => Char('\t'),
+        KeyCode::Up => Up,
+        KeyCode::Down => Down,
+        KeyCode::Left => Left,
+        KeyCode::Right => Right,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Esc => Key::Esc,
+        _ => return None,
+    })
+}
+
+// raw mode only lasts while reading keys, the rest of the program prints lines ending with \n
+struct RawMode;
+
+impl RawMode {
+    fn enable() -> RawMode {
+        let _ = terminal::enable_raw_mode();
+        RawMode
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+// waits up to timeout (for ever with None) for a key, resizes are recorded in RESIZED,
+// None once interrupted
+fn poll_key(timeout: Option<Duration>) -> Option<Key> {
+    let _raw = RawMode::enable();
+    let start = Instant::now();
+    loop {
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            return None
+        }
+        let step = Duration::from_millis(100);
+        let wait = timeout.map(|timeout| min(timeout.saturating_sub(start.elapsed()), step)).unwrap_or(step);
+        if !event::poll(wait).unwrap_or(false) {
+            if timeout.map(|timeout| start.elapsed() >= timeout).unwrap_or(false) {
+                return None
+            }
+            continue
+        }
+        match event::read() {
+            Ok(Event::Key(event)) => match to_key(event) {
+                Some(key) => return Some(key),
+                None if timeout.is_some() => return None,
+                None => (),
+            },
+            Ok(Event::Resize(_, _)) => {
+                RESIZED.store(true, Ordering::SeqCst);
+                if timeout.is_some() {
+                    return None
+                }
+            },
+            Ok(_) => (),
+            Err(_) => return None,
+        }
+    }
+}
+
+fn read_key() -> Option<Key> {
+    poll_key(None)
+}
+
+// for the subcommands changing subscriptions or watched videos, exits on a wrong pin
+fn require_pin(app_config: &AppConfig) {
+    if let Some(profile) = &app_config.restricted_profile {
+        print!("pin: ");
+        io::stdout().flush().unwrap();
+        let pin = read_pin();
+        println!();
+        if pin != profile.pin {
+            println!("wrong pin");
+            std::process::exit(1)
+        }
+    }
+}
+
+fn read_pin() -> String {
+    let mut pin = String::new();
+    loop {
+        match read_key() {
+            Some(Char('\n')) | Some(Key::Esc) | None => return pin,
+            Some(Key::Backspace) => {
+                pin.pop();
+            },
+            Some(Char(c)) => {
+                pin.push(c);
+                print!("*");
+                io::stdout().flush().unwrap();
+            },
+            _ => (),
+        }
+    }
+}
+
+// reads a line after prefix, up and down go through history, escape cancels
+fn read_line_with_history(prefix: &str, history: &[String]) -> String {
+    let mut line = String::new();
+    let mut position = history.len();
+    loop {
+        move_to_bottom();
+        clear_to_end_of_line();
+        print!("{}{}", prefix, line);
+        io::stdout().flush().unwrap();
+        match read_key() {
+            Some(Key::Esc) => return "".to_string(),
+            None if INTERRUPTED.load(Ordering::SeqCst) => return "".to_string(),
+            Some(Char('\n')) | None => return line,
+            Some(Key::Backspace) => {
+                line.pop();
+            },
+            Some(Up) if position > 0 => {
+                position -= 1;
+                line = history[position].to_string();
+            },
+            Some(Down) if position < history.len() => {
+                position += 1;
+                line = history.get(position).cloned().unwrap_or_default();
+            },
+            Some(Char(c)) => line.push(c),
+            _ => (),
+        }
+    }
+}
+
+fn pause() {
+    read_key();
+}
+
+// the resolved channel id and its videos, or why subscribing failed
+type Subscription = Result<(String, Vec<Video>), String>;
+
+// sent by the refresh worker, Subscriptions first when it had to list them
+enum Refreshed {
+    // the subscribed feeds and the number of stale ones about to be fetched
+    Subscriptions(Vec<String>, usize),
+    Feed(String, Fetched),
+    Failed(String),
+}
+
+// feeds fetched in the background by a hard refresh
+struct Refresh {
+    receiver: mpsc::Receiver<Refreshed>,
+    done: usize,
+    total: usize,
+    // channel of the last fetched feed
+    name: String,
+    // automatic refreshes are kept in pending until R is pressed
+    auto: bool,
+    new: Vec<Video>,
+}
+
+struct YoutubeSubscribtions {
+    n: usize,
+    start: usize,
+    filter: String,
+    i: usize,
+    toshow: Vec<Video>,
+    // number of videos in the list across all its pages
+    total_videos: usize,
+    // unwatched videos of the list across all its pages
+    unread: usize,
+    pinned: usize,
+    marked: Vec<String>,
+    videos: Videos,
+    state: State,
+    subscriptions_modified: (Option<SystemTime>, Option<SystemTime>),
+    translate_titles: bool,
+    translations: HashMap<String, String>,
+    theme: Theme,
+    saved_view: Option<(String, usize, usize)>,
+    // last `/` search, n and N move between its matches
+    last_search: Option<String>,
+    // url of the video shown in the preview pane
+    previewed: Option<String>,
+    // vim-like marks, m{a-z} sets them on the url of the selected video
+    marks: HashMap<char, String>,
+    // selected video before the last jump, for ''
+    previous_position: Option<String>,
+    // urls of the videos of the list across all its pages, in page order
+    listed_urls: Vec<String>,
+    // j and k move in the channel sidebar instead of the list
+    sidebar_focus: bool,
+    sidebar_i: usize,
+    // digits typed before G or enter to jump to a row
+    count: String,
+    group: Option<String>,
+    // continuation token of the next older page of each backfilled feed, None when exhausted
+    continuations: HashMap<String, Option<String>>,
+    refresh: Option<Refresh>,
+    // durations fetched in the background after a refresh
+    durations: Option<mpsc::Receiver<Vec<(String, u64)>>>,
+    // the pin was entered for the : command being run
+    command_unlocked: bool,
+    // channel id and videos of a subscription made from the list
+    subscription: Option<mpsc::Receiver<Subscription>>,
+    // views, likes and subscribers of the video shown by show_info
+    statistics: Option<mpsc::Receiver<(String, Statistics)>>,
+    pending: Vec<(String, Fetched)>,
+    pending_new: Vec<Video>,
+    // videos found by the last refresh, highlighted until the next one
+    new_urls: HashSet<String>,
+    last_refresh: Instant,
+    app_config: AppConfig,
+}
+
+// below this width, only the age and the title of videos are shown
+const MIN_COLS: usize = 40;
+
+const DATE_GROUPS: &[&str] = &["Today", "Yesterday", "This week", "Last week", "This month", "Older"];
+
+// groups by local day
+fn date_group(published: &str, now: i64) -> &'static str {
+    let timestamp = parse_timestamp(published);
+    let day = timestamp.map(|t| format_local_date(t, "%Y-%m-%d")).unwrap_or("".to_string());
+    match timestamp.map(|t| now - t).unwrap_or(i64::MAX) {
+        _ if day == format_local_date(now, "%Y-%m-%d") => DATE_GROUPS[0],
+        _ if day == format_local_date(now - 86400, "%Y-%m-%d") => DATE_GROUPS[1],
+        age if age < 7 * 86400 => DATE_GROUPS[2],
+        age if age < 14 * 86400 => DATE_GROUPS[3],
+        age if age < 31 * 86400 => DATE_GROUPS[4],
+        _ => DATE_GROUPS[5],
+    }
+}
+
+// below this width, the right preview pane and the channel sidebar are not shown
+const PANES_MIN_COLS: usize = 80;
+
+const SIDEBAR_COLS: usize = 28;
+
+fn sidebar_cols(app_config: &AppConfig) -> usize {
+    if app_config.channel_sidebar && get_cols() >= PANES_MIN_COLS {
+        SIDEBAR_COLS
+    } else {
+        0
+    }
+}
+
+// columns of the list, the right preview pane takes the rest
+fn list_cols(app_config: &AppConfig) -> usize {
+    let cols = get_cols();
+    if app_config.preview_pane == "right" && cols >= PANES_MIN_COLS {
+        cols * 3 / 5
+    } else {
+        cols
+    }
+}
+
+// lines of the bottom preview pane
+fn preview_lines(app_config: &AppConfig) -> usize {
+    if app_config.preview_pane == "bottom" {
+        get_lines() / 3
+    } else {
+        0
+    }
+}
+
+// lines left for the videos of a page, date headers are given one line each
+fn page_size(app_config: &AppConfig) -> usize {
+    let lines = std::cmp::max(get_lines() - preview_lines(app_config), 1);
+    if app_config.date_headers {
+        std::cmp::max(lines.saturating_sub(DATE_GROUPS.len()), 1)
+    } else {
+        lines
+    }
+}
+
+// splits text into lines of at most width characters, breaking between words
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let width = std::cmp::max(width, 1);
+    let mut lines = vec![];
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let length = line.chars().count();
+            if length > 0 && length + 1 + word.chars().count() > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+            while line.chars().count() > width {
+                let rest = line.chars().skip(width).collect::<String>();
+                lines.push(line.chars().take(width).collect());
+                line = rest;
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+// terminal graphics protocol used to draw thumbnails, sixel terminals cannot be detected
+fn thumbnail_protocol(app_config: &AppConfig) -> Option<&'static str> {
+    let env_is = |name: &str, values: &[&str]| env::var(name).map(|value| values.contains(&value.as_str())).unwrap_or(false);
+    match app_config.thumbnails.as_str() {
+        "kitty" => Some("kitty"),
+        "iterm2" => Some("iterm2"),
+        "sixel" => Some("sixel"),
+        "auto" if env::var("KITTY_WINDOW_ID").is_ok() || env_is("TERM", &["xterm-kitty"]) => Some("kitty"),
+        "auto" if env_is("TERM_PROGRAM", &["iTerm.app", "WezTerm"]) => Some("iterm2"),
+        _ => None,
+    }
+}
+
+// 64-bit FNV-1a, unlike DefaultHasher it does not change across rust releases
+fn fnv1a(s: &str) -> u64 {
+    s.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+// thumbnail paths being downloaded (true) or whose download failed (false)
+static THUMBNAIL_DOWNLOADS: Mutex<BTreeMap<String, bool>> = Mutex::new(BTreeMap::new());
+
+// set when a thumbnail download finished, the screens showing thumbnails then redraw
+static THUMBNAIL_DOWNLOADED: AtomicBool = AtomicBool::new(false);
+
+// the thumbnail of a video in a thumbnails directory next to the cache, downloaded once in
+// the background, draw_thumbnail shows a placeholder until the file exists
+fn thumbnail_path(v: &Video, app_config: &AppConfig) -> Option<String> {
+    if v.thumbnail.is_empty() {
+        return None
+    }
+    let directory = Path::new(&app_config.cache_path).parent()?.join("thumbnails");
+    let path = directory.join(format!("{:016x}", fnv1a(&v.thumbnail))).to_str()?.to_string();
+    if Path::new(&path).exists() {
+        return Some(path)
+    }
+    {
+        let mut downloads = THUMBNAIL_DOWNLOADS.lock().unwrap();
+        match downloads.get(&path) {
+            Some(true) => return Some(path),
+            Some(false) => return None,
+            None => downloads.insert(path.to_string(), true),
+        };
+    }
+    let url = v.thumbnail.to_string();
+    let app_config = app_config.clone();
+    let download_path = path.to_string();
+    runtime().spawn(async move {
+        let downloaded = fs::create_dir_all(&directory).is_ok() && download_thumbnail(&url, &download_path, &app_config).await.is_some();
+        if downloaded {
+            THUMBNAIL_DOWNLOADS.lock().unwrap().remove(&download_path);
+        } else {
+            THUMBNAIL_DOWNLOADS.lock().unwrap().insert(download_path, false);
+        }
+        THUMBNAIL_DOWNLOADED.store(true, Ordering::SeqCst);
+    });
+    Some(path)
+}
+
+async fn download_thumbnail(url: &str, path: &str, app_config: &AppConfig) -> Option<()> {
+    let response = call(http_get(url, app_config), app_config).await.filter(|response| response.status().is_success())?;
+    let bytes = response.bytes().await.ok()?;
+    write_atomically(path, &bytes).ok()
+}
+
+fn thumbnails_downloading() -> bool {
+    THUMBNAIL_DOWNLOADS.lock().unwrap().values().any(|downloading| *downloading)
+}
+
+// pixel size of a terminal cell, 8x16 when the terminal does not tell
+fn cell_size() -> (usize, usize) {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let known = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0
+        && size.ws_xpixel > 0 && size.ws_ypixel > 0 && size.ws_col > 0 && size.ws_row > 0;
+    if known {
+        ((size.ws_xpixel / size.ws_col) as usize, (size.ws_ypixel / size.ws_row) as usize)
+    } else {
+        (8, 16)
+    }
+}
+
+// youtube thumbnails are 4:3
+fn thumbnail_rows(cols: usize) -> usize {
+    let (width, height) = cell_size();
+    cols * width * 3 / (4 * height)
+}
+
+fn thumbnail_cols(rows: usize) -> usize {
+    let (width, height) = cell_size();
+    rows * height * 4 / (3 * width)
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut result = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, byte)| n | (*byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            result.push(if i <= chunk.len() { ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char } else { '=' });
+        }
+    }
+    result
+}
+
+// kitty images stay on screen until deleted
+fn clear_thumbnails(app_config: &AppConfig) {
+    if thumbnail_protocol(app_config) == Some("kitty") {
+        print!("\x1b_Ga=d\x1b\\");
+        io::stdout().flush().unwrap();
+    }
+}
+
+// draws an image fitting in the given cells, nothing is drawn when the protocol tool is missing
+fn draw_thumbnail(path: &str, protocol: &str, row: usize, col: usize, cols: usize, rows: usize) {
+    if !Path::new(path).exists() {
+        let downloading = THUMBNAIL_DOWNLOADS.lock().unwrap().get(path) == Some(&true);
+        move_cursor_to(row + rows / 2, col);
+        print!("{}", truncate_visible(if downloading { "loading thumbnail..." } else { "no thumbnail" }, cols));
+        return io::stdout().flush().unwrap()
+    }
+    move_cursor_to(row, col);
+    io::stdout().flush().unwrap();
+    match protocol {
+        "iterm2" => if let Ok(bytes) = fs::read(path) {
+            print!("\x1b]1337;File=inline=1;width={};height={};preserveAspectRatio=1:{}\x07", cols, rows, base64(&bytes));
+        },
+        "kitty" => {
+            let _res = Command::new("kitty")
+                .args(["+kitten", "icat", "--silent", "--stdin=no", "--transfer-mode=file"])
+                .arg(format!("--place={}x{}@{}x{}", cols, rows, col, row))
+                .arg(path)
+                .stderr(Stdio::null())
+                .status();
+        },
+        "sixel" => {
+            let (width, height) = cell_size();
+            let _res = Command::new("img2sixel")
+                .arg(format!("--width={}", min(cols * width, rows * height * 4 / 3)))
+                .arg(path)
+                .stderr(Stdio::null())
+                .status();
+        },
+        _ => (),
+    }
+    io::stdout().flush().unwrap();
+}
+
+// minimal width of the cells of the thumbnail grid
+const GRID_CELL_COLS: usize = 30;
+
+fn preview(v: &Video, width: usize, theme: &Theme) -> Vec<String> {
+    let mut lines = wrap(&v.title, width).iter().map(|line| colorize(&theme.highlight, line)).collect::<Vec<String>>();
+    lines.extend(wrap(&v.channel, width).iter().map(|line| colorize(&theme.channel, line)));
+    if let Some(published) = parse_timestamp(&v.published) {
+        let date = format!("{} ({} ago)", format_local_date(published, "%Y-%m-%d %H:%M"), format_age(now_timestamp() - published));
+        lines.push(colorize(&theme.date, &date));
+    }
+    if let Some(duration) = v.duration {
+        lines.push(format!("duration: {}", format_duration(duration)));
+    }
+    lines.push("".to_string());
+    lines.extend(wrap(&v.description, width));
+    lines
+}
+
+/// replaces the `{name}` and `{name:width}` placeholders of a row_format with the colored values of field
+fn format_row(template: &str, field: &dyn Fn(&str) -> Option<(String, String)>) -> String {
+    let mut result = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let placeholder = match rest[start..].find('}') {
+            Some(end) => &rest[start + 1..start + end],
+            None => break,
+        };
+        result.push_str(&rest[..start]);
+        rest = &rest[start + placeholder.len() + 2..];
+        let mut parts = placeholder.splitn(2, ':');
+        let name = parts.next().unwrap_or("");
+        let width = parts.next().and_then(|width| width.parse::<usize>().ok());
+        match field(name) {
+            Some((value, color)) => {
+                let value = match width {
+                    Some(width) => format!("{:<width$}", value.chars().take(width).collect::<String>(), width = width),
+                    None => value,
+                };
+                result.push_str(&colorize(&color, &value));
+            },
+            None => result.push_str(&format!("{{{}}}", placeholder)),
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+fn print_videos(toshow: &[Video], pinned: usize, marked: &[String], new: &HashSet<String>, state: &State, theme: &Theme, app_config: &AppConfig) {
+    let max = toshow.iter().fold(0, |acc, x| if x.channel.chars().count() > acc { x.channel.chars().count() } else { acc } );
+    let left = sidebar_cols(app_config);
+    let cols = list_cols(app_config) - left;
+    let now = now_timestamp();
+    let durations = toshow.iter().any(|video| video.duration.is_some());
+    let headers = app_config.date_headers && app_config.sort_order == "date";
+    let number_width = toshow.len().to_string().len();
+    let mut rows = vec![];
+    let mut row = 0;
+    let mut group = None;
+    for (i, video) in toshow.iter().enumerate() {
+        if headers && i >= pinned && row - i < DATE_GROUPS.len() {
+            let current = date_group(&video.published, now);
+            if group != Some(current) {
+                move_cursor_to(row, left);
+                print!("   {}", colorize(&theme.highlight, current));
+                group = Some(current);
+                row += 1;
+            }
+        }
+        move_cursor_to(row, left);
+        rows.push(row);
+        row += 1;
+        let date = parse_timestamp(&video.published).map(|t| format_local_date(t, &app_config.date_format))
+            .unwrap_or(video.published.get(5..10).unwrap_or("").to_string());
+        let whitespaces = " ".repeat(max - video.channel.chars().count());
+        let stale = app_config.stale_after_days > 0 && i >= pinned
+            && parse_timestamp(&video.published).map(|t| now - t > app_config.stale_after_days as i64 * 86400).unwrap_or(false);
+        let date_color = if i < pinned { &theme.pinned } else if stale { &theme.stale } else { &theme.date };
+        let mark = if marked.contains(&video.url) { "*" } else if new.contains(&video.url) { "+" } else { " " };
+        let mark = if app_config.row_numbers {
+            format!("{} {}", colorize(&theme.date, &format!("{:>width$}", i + 1, width = number_width)), mark)
+        } else {
+            mark.to_string()
+        };
+        let title_color = match get_id(video) {
+            Some(Some(id)) if state.watched.contains(&id) => &theme.watched,
+            _ if new.contains(&video.url) => &theme.new,
+            _ if stale => &theme.stale,
+            _ => "",
+        };
+        let channel_color = if in_channel_group(video, &app_config.pinned_channels) { &theme.priority } else if stale { &theme.stale } else { &theme.channel };
+        let link = |row: String| if app_config.hyperlinks {
+            hyperlink(&watch_url(video).unwrap_or(video.url.to_string()), &row)
+        } else {
+            row
+        };
+        if cols < MIN_COLS {
+            let age = parse_timestamp(&video.published).map(|t| format_age(now - t)).unwrap_or("".to_string());
+            let s = format!(" {}{}", mark, link(format!("{} {}", colorize(date_color, &format!("{:>3}", age)), colorize(title_color, &format!("{}{}", video_label(video, now), video.title)))));
+            print!("{}{}", truncate_visible(&s, cols.saturating_sub(1)), reset());
+            continue;
+        }
+        if !app_config.row_format.is_empty() {
+            let field = |name: &str| match name {
+                "date" => Some((date.to_string(), date_color.to_string())),
+                "age" => Some((parse_timestamp(&video.published).map(|t| format_age(now - t)).unwrap_or("".to_string()), date_color.to_string())),
+                "channel" => Some((video.channel.to_string(), channel_color.to_string())),
+                "title" => Some((format!("{}{}", video_label(video, now), video.title), title_color.to_string())),
+                "duration" => Some((video.duration.map(format_duration).unwrap_or("".to_string()), date_color.to_string())),
+                "views" => Some((video.views.map(format_count).unwrap_or("".to_string()), date_color.to_string())),
+                _ => None,
+            };
+            let s = format!(" {}{}", mark, link(format_row(&app_config.row_format, &field)));
+            print!("{}{}", truncate_visible(&s, cols.saturating_sub(2)), reset());
+            continue;
+        }
+        let duration = if durations {
+            format!(" {:>7}", video.duration.map(format_duration).unwrap_or("".to_string()))
+        } else {
+            "".to_string()
+        };
+        let s = format!(" {}{}", mark, link(format!("{}{} {}{} {}", colorize(date_color, &date), colorize(date_color, &duration), colorize(channel_color, &video.channel), whitespaces, colorize(title_color, &format!("{}{}", video_label(video, now), video.title)))));
+        print!("{}{}", truncate_visible(&s, cols.saturating_sub(2)), reset());
+    }
+    io::stdout().flush().unwrap();
+    VIDEO_ROWS.with(|video_rows| *video_rows.borrow_mut() = rows);
+    LIST_LEFT.with(|list_left| list_left.set(left));
+}
+
+fn get_resumable_ids(app_config: &AppConfig) -> Vec<String> {
+    match fs::read_dir(&app_config.watch_later_path) {
+        Ok(entries) => entries.flat_map( |entry| {
+            entry.ok()
+                .and_then(|e| fs::read_to_string(e.path()).ok())
+                .and_then(|content| content.lines().next().map(|l| l.to_string()))
+                .and_then(|line| line.split("v=").nth(1).or(line.split("/").last()).map(|id| id.to_string()))
+        }).collect(),
+        Err(_) => vec![],
+    }
+}
+
+fn get_id(v: &Video) -> Option<Option<String>> {
+    v.url.split("/").collect::<Vec<&str>>().last().map( |page|
+                                                        page.split("?").collect::<Vec<&str>>().first().map( |s| s.to_string() ))
+}
+
+fn read_command_output(command: &mut Command, binary: &String) {
+    match command.stdout(Stdio::piped())
+        .spawn() {
+            Ok(spawn) => {
+                if let Some(stdout) = spawn.stdout {
+                    for byte in io::BufReader::new(stdout).bytes() {
+                        print!("{}", byte.unwrap() as char);
+                        io::stdout().flush().unwrap();
+                    }
+                }
+            },
+            Err(e) => {
+                if let NotFound = e.kind() {
+                    println!("`{}` was not found: maybe you should install it ?", binary)
+                } else {
+                    println!("error while runnnig {} : {}", binary, e);
+                }
+                pause();
+            }
+        }
+}
+
+struct PlaySettings<'a> {
+    youtubedl_format: &'a String,
+    video_extension: &'a String,
+    players: &'a Vec<Vec<String>>,
+    extra_args: &'a [String],
+    proxy: Option<String>,
+}
+
+// settings of the first `channel_overrides` entry matching the video channel,
+// falling back on the global configuration
+fn play_settings<'a>(v: Option<&Video>, app_config: &'a AppConfig) -> PlaySettings<'a> {
+    let channel_override = v.and_then(|v| app_config.channel_overrides.iter()
+                                      .find(|(channel, _)| in_channel_group(v, &[channel.to_string()]))
+                                      .map(|(_, channel_override)| channel_override));
+    PlaySettings {
+        youtubedl_format: channel_override.and_then(|o| o.youtubedl_format.as_ref()).unwrap_or(&app_config.youtubedl_format),
+        video_extension: channel_override.and_then(|o| o.video_extension.as_ref()).unwrap_or(&app_config.video_extension),
+        players: channel_override.and_then(|o| o.players.as_ref()).unwrap_or(&app_config.players),
+        extra_args: channel_override.map(|o| o.extra_args.as_slice()).unwrap_or(&[]),
+        proxy: proxy_url(app_config),
+    }
+}
+
+fn play_video(path: &String, settings: &PlaySettings) {
+    for player in settings.players {
+        if fs::metadata(&player[0]).is_ok() {
+
+            let mut child1 = Command::new(&player[0]);
+            for arg in &player[1..] {
+                child1.arg(arg);
+            } 
+            read_command_output(child1.args(settings.extra_args).arg(path), &player[0]);
+            return
+        }
+    }
+}
+
+fn download_video(path: &String, id: &String, settings: &PlaySettings) {
+    if fs::metadata(path).is_err() {
+        read_command_output(Command::new("youtube-dl")
+            .args(settings.proxy.iter().flat_map(|proxy| vec!["--proxy".to_string(), proxy.to_string()]))
+            .arg("-f")
+            .arg(settings.youtubedl_format)
+            .arg("-o")
+            .arg(path)
+            .arg("--")
+            .arg(id), &"youtube-dl".to_string())
+    }
+}
+
+fn is_youtube(v: &Video) -> bool {
+    v.url.contains("youtube.com/")
+}
+
+fn watch_url(v: &Video) -> Option<String> {
+    match get_id(v) {
+        Some(Some(id)) if is_youtube(v) => Some(format!("https://www.youtube.com/watch?v={}", id)),
+        Some(Some(_)) => Some(v.url.to_string()),
+        _ => None,
+    }
+}
+
+fn play_id(id: &String, app_config: &AppConfig) {
+    play_url(&format!("https://www.youtube.com/watch?v={}", id), id, &play_settings(None, app_config), app_config);
+}
+
+// counts the seconds mpv spends playing (not paused), asking it through its IPC socket
+fn track_playback(socket: String, done: Arc<AtomicBool>) -> std::thread::JoinHandle<u64> {
+    std::thread::spawn(move || {
+        let mut seconds = 0;
+        while !done.load(Ordering::SeqCst) {
+            std::thread::sleep(Duration::from_secs(1));
+            let mut stream = match UnixStream::connect(&socket) {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            };
+            let _res = stream.set_read_timeout(Some(Duration::from_millis(500)));
+            if stream.write_all(b"{\"command\": [\"get_property\", \"pause\"]}\n").is_err() {
+                continue;
+            }
+            // mpv may send events before the reply
+            let reply = io::BufReader::new(stream).lines().take(10)
+                .flat_map(|line| line.ok())
+                .find(|line| line.contains("\"error\""));
+            if reply.map(|line| line.contains("\"data\":false")).unwrap_or(false) {
+                seconds += 1;
+            }
+        }
+        seconds
+    })
+}
+
+/// plays url and returns the number of seconds it was played
+fn play_url(url: &String, id: &String, settings: &PlaySettings, app_config: &AppConfig) -> u64 {
+    if app_config.mpv_mode && fs::metadata(&app_config.mpv_path).is_ok() {
+        let message = format!("playing {} with mpv...", url);
+        debug(&message);
+        let socket = format!("/tmp/yts_mpv_{}.sock", std::process::id());
+        let done = Arc::new(AtomicBool::new(false));
+        let tracker = track_playback(socket.to_string(), done.clone());
+        read_command_output(
+            Command::new(&app_config.mpv_path)
+            .arg("-fs")
+            .arg("-really-quiet")
+            .arg("--ytdl-format")
+            .arg(settings.youtubedl_format)
+            .arg(if app_config.read_only { "--no-save-position-on-quit" } else { "--save-position-on-quit" })
+            .arg("--write-filename-in-watch-later-config")
+            .arg(format!("--watch-later-directory={}", app_config.watch_later_path))
+            .args(settings.proxy.iter().map(|proxy| format!("--ytdl-raw-options=proxy={}", proxy)))
+            .args(settings.extra_args)
+            .arg(format!("--input-ipc-server={}", socket))
+            .arg(url)
+            , &app_config.mpv_path);
+        done.store(true, Ordering::SeqCst);
+        let _res = fs::remove_file(&socket);
+        tracker.join().unwrap_or(0)
+    } else {
+        clear();
+        move_cursor(0);
+        let path = format!("{}/{}.{}", app_config.video_path, id, settings.video_extension);
+        download_video(&path, url, settings);
+        let start = Instant::now();
+        play_video(&path, settings);
+        start.elapsed().as_secs()
+    }
+}
+
+fn play(v: &Video, app_config: &AppConfig) -> u64 {
+    set_title(&v.title);
+    match (get_id(v), watch_url(v)) {
+        (Some(Some(id)), Some(url)) => play_url(&url, &id, &play_settings(Some(v), app_config), app_config),
+        _ => 0,
+    }
+}
+
+fn shuffle_play(videos: &mut [Video], app_config: &AppConfig) {
+    videos.sort_by_cached_key(|video| (random_fraction(&video.url) * 1e9) as u64);
+    if app_config.mpv_mode && fs::metadata(&app_config.mpv_path).is_ok() {
+        debug(&format!("playing {} videos with mpv...", videos.len()));
+        read_command_output(
+            Command::new(&app_config.mpv_path)
+            .arg("-fs")
+            .arg("-really-quiet")
+            .arg("--ytdl-format")
+            .arg(&app_config.youtubedl_format)
+            .args(proxy_url(app_config).iter().map(|proxy| format!("--ytdl-raw-options=proxy={}", proxy)))
+            .args(videos.iter().flat_map(watch_url))
+            , &app_config.mpv_path);
+    } else {
+        for video in videos.iter() {
+            play(video, app_config);
+        }
+    }
+}
+
+fn write_batch_file(videos: &[Video], app_config: &AppConfig) -> Result<usize, Error> {
+    let urls = videos.iter().flat_map(watch_url).collect::<Vec<String>>();
+    fs::write(&app_config.batch_file_path, urls.join("\n") + "\n")?;
+    Ok(urls.len())
+}
+
+fn download_batch_file(app_config: &AppConfig) {
+    read_command_output(Command::new("youtube-dl")
+        .args(proxy_url(app_config).iter().flat_map(|proxy| vec!["--proxy".to_string(), proxy.to_string()]))
+        .arg("-f")
+        .arg(&app_config.youtubedl_format)
+        .arg("-o")
+        .arg(format!("{}/%(id)s.{}", app_config.video_path, app_config.video_extension))
+        .arg("--batch-file")
+        .arg(&app_config.batch_file_path), &"youtube-dl".to_string())
+}
+
+type Action = fn(&mut YoutubeSubscribtions);
+
+// keys of the list and their help, the entries without keys are handled in run()
+const KEY_BINDINGS: &[(&[Key], &str, &str, Action)] = &[
+    (&[], "q,ctrl-c", "quit", |_| ()),
+    (&[Key::Ctrl('z')], "ctrl-z", "suspend, fg to resume", |yts| yts.suspend()),
+    (&[Char('j'), Char('l'), Down], "j,l,down", "move down", |yts| yts.i = jump(yts.i, yts.i + 1)),
+    (&[Char('k'), Up], "k,up", "move up", |yts| yts.i = jump(yts.i, if yts.i > 0 { yts.i - 1 } else { yts.n - 1 })),
+    (&[Char('g'), Char('H')], "g,H", "go to top", |yts| yts.i = jump(yts.i, 0)),
+    (&[Char('G'), Char('L')], "G,L", "go to bottom", |yts| yts.i = jump(yts.i, yts.n - 1)),
+    (&[Char('M')], "M", "go to middle", |yts| yts.i = jump(yts.i, yts.n / 2)),
+    (&[Char('m')], "m{a-z}", "set a mark on the selected video", |yts| yts.set_mark()),
+    (&[Char('\'')], "'{a-z}", "go to a mark, '' goes back to the video selected before the last jump", |yts| yts.jump_to_mark()),
+    (&[], "<n>G,<n>g", "go to the video number n of the page (numbers shown with row_numbers), also <n>enter", |_| ()),
+    (&[Char('r'), Char('$'), Left], "r,$,left", "soft refresh", |yts| yts.soft_reload()),
+    (&[Char('P')], "P", "previous page (older videos, newer ones with newest_first)", |yts| yts.previous_page()),
+    (&[Char('N')], "N", "next page", |yts| yts.next_page()),
+    (&[Char('R')], "R", "full refresh (fetches video list), or merge the new videos of an automatic refresh", |yts| yts.hard_reload()),
+    (&[Char('h'), Char('?')], "h,?", "shows this help", |yts| yts.help()),
+    (&[Char('i'), Right], "i,right", "video information, scrolled with j and k, <n>o opens the link n of the description and <n>y copies it", |yts| yts.info()),
+    (&[Char('I')], "I", "cycle the preview pane of the selected video between right, bottom and hidden", |yts| yts.cycle_preview_pane()),
+    (&[Char('/')], "/", "search titles, channels and descriptions (re:<regex> for a regular expression)", |yts| yts.search()),
+    (&[Char('n')], "n", "next match of the last search", |yts| yts.next_match(true)),
+    (&[Char('b')], "b", "previous match of the last search", |yts| yts.next_match(false)),
+    (&[Char('f')], "f", "filter (channel:<name> to show only one channel, re:<regex> for a regular expression,
+>20m or <5m for the videos longer or shorter than a duration,
+prefixed with ! or - to hide the matching videos instead)
+up and down go through previous inputs of /, f and :", |yts| yts.filter()),
+    (&[Char('W')], "W", "grid of the thumbnails of the page (hjkl to move, p to play, i for information, q to go back)", |yts| yts.grid()),
+    (&[Char('\t')], "tab", "show the channel sidebar, or switch between it and the list", |yts| yts.toggle_sidebar_focus()),
+    (&[Char('C')], "C", "channel list, enter filters the list on the selected channel", |yts| yts.channels()),
+    (&[Char('D')], "D", "show only the selected video channel, press again to go back", |yts| yts.drill_down()),
+    (&[Char('c')], "c", "filter on the selected video channel, press again to clear the filter", |yts| yts.toggle_channel_filter()),
+    (&[Char('t')], "t", "cycle between channel_tags groups", |yts| yts.cycle_group()),
+    (&[Char('v')], "v", "cycle between saved_filters views", |yts| yts.cycle_saved_filter()),
+    (&[Char('s')], "s", "hide/show youtube shorts", |yts| yts.toggle_shorts()),
+    (&[Char('O')], "O", "cycle between sort orders (date, channel, title, duration, added)", |yts| yts.cycle_sort_order()),
+    (&[Char('F')], "F", "flip the list to show the newest videos at the top or at the bottom", |yts| yts.toggle_newest_first()),
+    (&[Char('X')], "X", "mute (hide) selected video channel, for ever or for some days", |yts| yts.mute_current()),
+    (&[Char('T')], "T", "toggle translation of titles (requires translate_command)", |yts| yts.toggle_translation()),
+    (&[Char('p'), Char('\n')], "p,enter", "plays selected video", |yts| yts.play_current()),
+    (&[Char('o')], "o", "open selected video in browser", |yts| yts.open_current()),
+    (&[Char(' ')], "space", "mark/unmark selected video", |yts| yts.toggle_mark()),
+    (&[Char('A')], "A", "mark all the videos of the list as watched, :mark-all-read all for every cached video", |yts| yts.mark_all_read(false)),
+    (&[Char('B')], "B", "export marked (or filtered) videos to a youtube-dl batch file", |yts| yts.export_batch()),
+    (&[Char('S')], "S", "subscribe to a channel (id, url, @handle or video url), also :subscribe <channel>", |yts| yts.subscribe_prompt()),
+    (&[Char('U')], "U", "unsubscribe from selected video channel", |yts| yts.unsubscribe_current()),
+    (&[Char(':')], ":", "run a command", |yts| yts.command()),
+];
+
+const COMMANDS_HELP: &str = ":find-channel <query>  search channels (requires invidious_instances)
+:presentation on|off   toggle read-only presentation mode
+:unmute <channel|all>  show a muted channel again
+:mix <name|all>        only show the channels of a channel_tags mix
+:shuffle <name>        play the unwatched videos of a mix in random order
+:mark-watched <expr>   mark videos matching expr as watched, for example
+                       channel:\"Some Channel\" after:2020-01-01 before:2020-02-01 keyword -other
+:unmark <expr>         mark videos matching expr as not watched
+:mark-all-read [all]   mark the videos of the list (or all of them) as watched";
+
+fn help_lines() -> Vec<String> {
+    let mut lines = vec!["youtube-subscriptions: a tool to view your youtube subscriptions in a terminal".to_string(), "".to_string()];
+    for (_, keys, description, _) in KEY_BINDINGS {
+        for (i, line) in description.lines().enumerate() {
+            lines.push(format!("{:<10} {}", if i == 0 { keys } else { "" }, line));
+        }
+    }
+    lines.extend(COMMANDS_HELP.lines().map(|line| line.to_string()));
+    lines
+}
+
+fn detect_language(text: &str) -> Option<String> {
+    whatlang::detect(text).filter(|info| info.is_reliable()).map(|info| info.lang().code().to_string())
+}
+
+fn needs_translation(text: &str, app_config: &AppConfig) -> bool {
+    !app_config.translate_command.is_empty() && match detect_language(text) {
+        Some(lang) => !app_config.native_languages.contains(&lang),
+        None => false,
+    }
+}
+
+fn translate(text: &str, app_config: &AppConfig) -> Option<String> {
+    Command::new(&app_config.translate_command[0])
+        .args(&app_config.translate_command[1..])
+        .arg(text)
+        .stderr(Stdio::null())
+        .output().ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// the information of a video, wrapped to width
+fn info_lines(v: &Video, width: usize, app_config: &AppConfig, theme: &Theme) -> Vec<String> {
+    let link = |line: &str| if app_config.hyperlinks { link_urls(line) } else { line.to_string() };
+    let mut lines = wrap(&v.title, width).iter().map(|line| if app_config.hyperlinks {
+        hyperlink(&watch_url(v).unwrap_or(v.url.to_string()), line)
+    } else {
+        line.to_string()
+    }).collect::<Vec<String>>();
+    lines.push("".to_string());
+    lines.extend(wrap(&match &v.subscribers {
+        Some(subscribers) => format!("from {} ({} subscribers)", v.channel, subscribers),
+        None => format!("from {}", v.channel),
+    }, width));
+    if let Some(duration) = v.duration {
+        lines.push(format!("duration: {}", format_duration(duration)));
+    }
+    if let Some(views) = v.views {
+        lines.push(format!("views: {}", format_count(views)));
+    }
+    if let Some(likes) = v.likes {
+        lines.push(format!("likes: {}", format_count(likes)));
+    }
+    if let Some(lang) = detect_language(&format!("{} {}", v.title, v.description)) {
+        lines.push(format!("language: {}", lang));
+    }
+    lines.push("".to_string());
+    lines.extend(wrap(&v.description, width).iter().map(|line| link(line)));
+    if needs_translation(&format!("{} {}", v.title, v.description), app_config) {
+        if let Some(title) = translate(&v.title, app_config) {
+            lines.push("".to_string());
+            lines.extend(wrap(&format!("translation: {}", title), width).into_iter().enumerate()
+                .map(|(i, line)| if i == 0 { line.replacen("translation:", &colorize(&theme.highlight, "translation:"), 1) } else { line }));
+            lines.push("".to_string());
+            lines.extend(wrap(&translate(&v.description, app_config).unwrap_or("".to_string()), width).iter().map(|line| link(line)));
+        }
+    }
+    let urls = extract_urls(&v.description);
+    if !urls.is_empty() {
+        lines.push("".to_string());
+        lines.push(colorize(&theme.highlight, "links:"));
+        lines.extend(urls.iter().enumerate().map(|(i, url)| format!("{:>3} {}", i + 1, link(url))));
+    }
+    lines
+}
+
+fn quit(app_config: &AppConfig) {
+    show_cursor();
+    pop_title();
+    if app_config.inline {
+        move_to_bottom();
+        println!();
+    } else {
+        rmcup();
+    }
+}
+
+// restores the terminal before the panic message is printed, so that it stays readable
+fn quit_on_panic(app_config: &AppConfig) {
+    let app_config = app_config.clone();
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if std::thread::current().name() == Some("main") {
+            quit(&app_config);
+        }
+        default_hook(info);
+    }));
+}
+
+impl YoutubeSubscribtions {
+
+    fn clear_and_print_videos(&mut self) {
+        self.previewed = None;
+        clear();
+        clear_thumbnails(&self.app_config);
+        move_cursor(0);
+        if self.translate_titles {
+            let app_config = &self.app_config;
+            let translations = &mut self.translations;
+            let toshow = self.toshow.iter().map( |video| {
+                let mut video = video.clone();
+                if needs_translation(&video.title, app_config) {
+                    let title = translations.entry(video.url.to_string()).or_insert_with(||
+                        translate(&video.title, app_config).unwrap_or(video.title.to_string()));
+                    video.title = title.to_string();
+                }
+                video
+            }).collect::<Vec<Video>>();
+            print_videos(&toshow, self.pinned, &self.marked, &self.new_urls, &self.state, &self.theme, &self.app_config)
+        } else {
+            print_videos(&self.toshow, self.pinned, &self.marked, &self.new_urls, &self.state, &self.theme, &self.app_config)
+        }
+        self.print_sidebar();
+        set_title(&format!("youtube-subscriptions — {} unread", self.unread));
+    }
+
+    fn toggle_translation(&mut self) {
+        if self.app_config.translate_command.is_empty() {
+            return debug(&"translate_command is not configured".to_string())
+        }
+        self.translate_titles = !self.translate_titles;
+        if self.translate_titles {
+            debug(&"translating titles...".to_string());
+        }
+        self.clear_and_print_videos();
+    }
+
+    fn pin_continue_watching(&mut self) {
+        self.pinned = 0;
+        if self.start != 0 || !self.filter.is_empty() {
+            return
+        }
+        let ids = get_resumable_ids(&self.app_config);
+        let mut resumable = self.videos.videos.iter().filter(|v|
+            match get_id(v) {
+                Some(Some(id)) => ids.contains(&id),
+                _ => false,
+            }).take(self.n / 2).cloned().collect::<Vec<Video>>();
+        if !resumable.is_empty() {
+            let keep = min(self.toshow.len(), self.n - resumable.len());
+            // the newest videos of the page are kept
+            let rest = if self.app_config.newest_first {
+                self.toshow.drain(..keep).collect()
+            } else {
+                self.toshow.split_off(self.toshow.len() - keep)
+            };
+            self.pinned = resumable.len();
+            resumable.extend(rest);
+            self.toshow = resumable;
+        }
+    }
+
+    fn move_page(&mut self, direction: i8) {
+        self.n = page_size(&self.app_config);
+        if direction == 1 {
+            if self.start + 2 * self.n < self.videos.videos.len() {
+                self.start += self.n;
+            }
+        }
+        else if direction == 0 {
+            self.start = 0;
+        }
+        else if direction == -1 {
+            if self.n > self.start {
+                self.start = 0;
+            }
+            else {
+                self.start -= self.n;
+            }
+        }
+        self.show_page()
+    }
+
+    fn refresh_toshow(&mut self, end: usize) {
+        let channel_tags = &self.app_config.channel_tags;
+        let group = self.group.as_ref().and_then(|name| channel_tags.get(name));
+        let state = &self.state;
+        let now = now_timestamp();
+        let allowed = self.app_config.restricted_profile.as_ref().map(|profile| &profile.allowed_channels);
+        let hide_shorts = self.app_config.hide_shorts;
+        let hide_premieres = self.app_config.hide_upcoming_premieres;
+        let max_age = self.app_config.max_video_age_days as i64 * 86400;
+        let newest = Some(self.app_config.max_per_channel)
+            .filter(|max| *max > 0 && !self.filter.starts_with("channel:"))
+            .map(|max| newest_per_channel(&self.videos.videos, max));
+        let blocked = self.app_config.blocked_title_patterns.iter()
+            .flat_map(|pattern| regex::Regex::new(pattern).ok())
+            .collect::<Vec<regex::Regex>>();
+        let keep = |video: &Video| group.map(|g| in_channel_group(video, g)).unwrap_or(true) && !is_muted(video, state, now)
+            && allowed.map(|a| in_channel_group(video, a)).unwrap_or(true)
+            && !blocked.iter().any(|re| re.is_match(&video.title))
+            && !(hide_shorts && is_short(video))
+            && !(hide_premieres && video.premiere.map(|premiere| premiere > now).unwrap_or(false))
+            && (max_age == 0 || parse_timestamp(&video.published).map(|t| now - t <= max_age).unwrap_or(true))
+            && newest.as_ref().map(|newest| newest.contains(&video.url)).unwrap_or(true);
+        let pinned_channels = &self.app_config.pinned_channels;
+        let float_pinned = self.app_config.float_pinned_channels;
+        let first = |video: &Video| float_pinned && in_channel_group(video, pinned_channels);
+        let (toshow, listed) = to_show_videos(&mut self.videos.videos, self.start, end, &self.filter, self.app_config.case_sensitive_search, &self.app_config.sort_order, &keep, &first);
+        self.toshow = toshow;
+        self.total_videos = listed.len();
+        self.listed_urls = listed.iter().map(|video| video.url.to_string()).collect();
+        self.unread = listed.iter().filter(|video| !matches!(get_id(video), Some(Some(id)) if state.watched.contains(&id))).count();
+        if self.app_config.newest_first {
+            self.toshow.reverse();
+        }
+        self.pin_continue_watching();
+    }
+
+    fn mute_current(&mut self) {
+        if self.i >= self.toshow.len() || !self.writable() {
+            return
+        }
+        let channel = self.toshow[self.i].channel.to_string();
+        let days = self.input_with_prefix(&format!("mute {} for how many days (empty for ever) ? ", channel));
+        hide_cursor();
+        let until = match days.trim() {
+            "" => None,
+            days => match days.parse::<i64>().ok().filter(|days| *days > 0)
+                .and_then(|days| days.checked_mul(86400)).and_then(|seconds| now_timestamp().checked_add(seconds)) {
+                Some(until) => Some(until),
+                None => {
+                    self.soft_reload();
+                    return debug(&format!("invalid number of days: {}", days))
+                },
+            },
+        };
+        self.state.muted.retain(|m| m.channel != channel);
+        self.state.muted.push(MutedChannel { channel: channel.to_string(), until });
+        save_state(&mut self.state, &self.app_config);
+        self.soft_reload();
+        debug(&format!("muted {}, use :unmute {} to show it again", channel, channel));
+    }
+
+    fn unmute(&mut self, channel: &str) {
+        let count = self.state.muted.len();
+        self.state.muted.retain(|m| channel != "all" && m.channel != channel);
+        save_state(&mut self.state, &self.app_config);
+        self.soft_reload();
+        debug(&format!("unmuted {} channels", count - self.state.muted.len()));
+    }
+
+    fn cycle_group(&mut self) {
+        let names = self.app_config.channel_tags.keys().cloned().collect::<Vec<String>>();
+        if names.is_empty() {
+            return debug(&"no channel_tags in configuration".to_string())
+        }
+        self.group = match &self.group {
+            None => Some(names[0].to_string()),
+            Some(current) => names.iter().position(|name| name == current)
+                .and_then(|pos| names.get(pos + 1)).cloned(),
+        };
+        self.soft_reload();
+        debug(&format!("group: {}", self.group.as_deref().unwrap_or("all")));
+    }
+
+    fn cycle_saved_filter(&mut self) {
+        let saved = &self.app_config.saved_filters;
+        if saved.is_empty() {
+            return debug(&"no saved_filters in configuration".to_string())
+        }
+        let next = match saved.iter().position(|(_, filter)| *filter == self.filter) {
+            None => saved.iter().next(),
+            Some(pos) => saved.iter().nth(pos + 1),
+        };
+        let (name, filter) = next.map(|(name, filter)| (name.to_string(), filter.to_string()))
+            .unwrap_or(("all".to_string(), "".to_string()));
+        self.filter = filter;
+        self.i = 0;
+        self.move_page(0);
+        self.clear_and_print_videos();
+        debug(&format!("view: {}", name));
+    }
+
+    fn toggle_shorts(&mut self) {
+        self.app_config.hide_shorts = !self.app_config.hide_shorts;
+        self.soft_reload();
+        debug(&format!("shorts {}", if self.app_config.hide_shorts { "hidden" } else { "shown" }));
+    }
+
+    fn toggle_newest_first(&mut self) {
+        self.app_config.newest_first = !self.app_config.newest_first;
+        self.soft_reload();
+        debug(&format!("{} first", if self.app_config.newest_first { "newest" } else { "oldest" }));
+    }
+
+    fn cycle_sort_order(&mut self) {
+        let position = SORT_ORDERS.iter().position(|order| *order == self.app_config.sort_order).unwrap_or(0);
+        self.app_config.sort_order = SORT_ORDERS[(position + 1) % SORT_ORDERS.len()].to_string();
+        self.soft_reload();
+        debug(&format!("sorted by {}", self.app_config.sort_order));
+    }
+
+    fn set_mix(&mut self, name: &str) {
+        if name == "all" {
+            self.group = None;
+        } else if self.app_config.channel_tags.contains_key(name) {
+            self.group = Some(name.to_string());
+        } else {
+            self.soft_reload();
+            return debug(&format!("no mix named {} in channel_tags", name))
+        }
+        self.start = 0;
+        self.soft_reload();
+        debug(&format!("group: {}", name));
+    }
+
+    fn shuffle_mix(&mut self, name: &str) {
+        let channels = match self.app_config.channel_tags.get(name) {
+            Some(channels) => channels,
+            None => {
+                self.soft_reload();
+                return debug(&format!("no mix named {} in channel_tags", name))
+            },
+        };
+        let now = now_timestamp();
+        let mut videos = self.videos.videos.iter().filter(|video| {
+            in_channel_group(video, channels) && !is_muted(video, &self.state, now)
+                && !matches!(get_id(video), Some(Some(id)) if self.state.watched.contains(&id))
+        }).cloned().collect::<Vec<Video>>();
+        clear();
+        move_cursor(0);
+        shuffle_play(&mut videos, &self.app_config);
+        self.soft_reload();
+    }
+
+    fn show_page(&mut self) {
+        self.refresh_toshow(self.start + self.n);
+        self.i = 0;
+        self.clear_and_print_videos()
+    }
+
+    fn next_page(&mut self) {
+        if self.app_config.newest_first {
+            self.older_page()
+        } else {
+            self.move_page(-1)
+        }
+    }
+
+    fn previous_page(&mut self) {
+        if self.app_config.newest_first {
+            self.move_page(-1)
+        } else {
+            self.older_page()
+        }
+    }
+
+    fn older_page(&mut self) {
+        self.move_page(1);
+        if self.toshow.len() < self.n && self.filter.starts_with("channel:") {
+            self.backfill();
+        }
+    }
+
+    // fetches older videos of the filtered channel once its cached videos are all shown
+    fn backfill(&mut self) {
+        if self.app_config.invidious_instances.is_empty() {
+            return debug(&"fetching older videos requires invidious_instances in configuration".to_string())
+        }
+        let channel = self.filter["channel:".len()..].to_string();
+        let case_sensitive = self.app_config.case_sensitive_search;
+        let feed = match self.videos.videos.iter().find(|video| in_channel(video, &channel, case_sensitive) && video.feed.contains("channel_id=")) {
+            Some(video) => video.feed.to_string(),
+            None => return,
+        };
+        let continuation = match self.continuations.get(&feed) {
+            Some(None) => return debug(&format!("no older videos for {}", channel)),
+            Some(Some(continuation)) => Some(continuation.to_string()),
+            None => None,
+        };
+        debug(&format!("fetching older videos of {}...", channel));
+        let channel_id = feed.split("channel_id=").nth(1).unwrap_or("");
+        match runtime().block_on(get_invidious_channel_page(channel_id, continuation.as_deref(), &self.app_config)) {
+            Some((videos, next)) => {
+                let known = self.videos.videos.iter().map(|video| video.url.to_string()).collect::<HashSet<String>>();
+                let older = videos.into_iter().filter(|video| !known.contains(&video.url)).map(|video| Video { feed: feed.to_string(), ..video }).collect::<Vec<Video>>();
+                let count = older.len();
+                self.videos.videos.extend(older);
+                self.continuations.insert(feed, next);
+                save_videos(&mut self.videos, &self.app_config);
+                self.show_page();
+                debug(&format!("fetched {} older videos of {}", count, channel));
+            },
+            None => debug(&format!("could not fetch older videos of {}", channel)),
+        }
+    }
+
+    fn soft_reload(&mut self) {
+        self.move_page(0);
+    }
+
+    // fetches the feeds in the background, they are merged in the list by receive_refresh
+    fn hard_reload(&mut self) {
+        if !self.pending.is_empty() {
+            return self.merge_pending()
+        }
+        self.start_refresh(false);
+    }
+
+    fn start_refresh(&mut self, auto: bool) {
+        if self.refresh.is_some() {
+            return debug(&"already updating video list".to_string())
+        }
+        self.last_refresh = Instant::now();
+        if !auto {
+            debug(&"updating video list...".to_string());
+        }
+        self.fetch_in_background(None, auto);
+    }
+
+    fn merge_pending(&mut self) {
+        for (url, result) in self.pending.drain(..) {
+            merge_fetched(&mut self.videos, url, result, &self.app_config);
+        }
+        save_videos(&mut self.videos, &self.app_config);
+        self.fetch_durations_in_background();
+        if self.pending_new.is_empty() {
+            self.redraw();
+        } else {
+            let new = std::mem::take(&mut self.pending_new);
+            self.show_new(new);
+        }
+    }
+
+    // fetches feeds, or with None the stale feeds of the subscriptions, which
+    // may have to be listed from the youtube account first
+    fn fetch_in_background(&mut self, feeds: Option<Vec<String>>, auto: bool) {
+        if self.refresh.is_some() {
+            return debug(&"already updating video list".to_string())
+        }
+        let validators = self.videos.validators.clone();
+        let fetched = self.videos.fetched.clone();
+        let app_config = self.app_config.clone();
+        let (sender, receiver) = mpsc::channel();
+        let total = feeds.as_ref().map(|feeds| feeds.len()).unwrap_or(0);
+        self.refresh = Some(Refresh { receiver, done: 0, total, name: "".to_string(), auto, new: vec![] });
+        runtime().spawn(async move {
+            let feeds = match feeds {
+                Some(feeds) => feeds,
+                None => match get_subscribed_urls(&app_config).await {
+                    Some(urls) => {
+                        let stale = get_stale_feeds(&urls, &fetched, &app_config);
+                        let _res = sender.send(Refreshed::Subscriptions(urls, stale.len()));
+                        stale
+                    },
+                    None => {
+                        let _res = sender.send(Refreshed::Failed("could not read subscriptions".to_string()));
+                        return
+                    },
+                },
+            };
+            let (validators, app_config, sender) = (&validators, &app_config, &sender);
+            // each feed is sent as soon as it is fetched
+            let fetch = move |url: String| async move {
+                let fetched = fetch_feed_videos(&url, validators.get(&url), app_config).await;
+                let _res = sender.send(Refreshed::Feed(url, fetched));
+            };
+            if auto && app_config.refresh_spread_seconds > 0 {
+                get_videos_spread(&feeds, fetch, app_config).await;
+                return
+            }
+            stream::iter(feeds).for_each_concurrent(CONCURRENT_REQUESTS, fetch).await;
+        });
+    }
+
+    fn receive_refresh(&mut self) {
+        let refresh = match &mut self.refresh {
+            Some(refresh) => refresh,
+            None => return,
+        };
+        let mut received = false;
+        let finished = loop {
+            match refresh.receiver.try_recv() {
+                Ok(Refreshed::Subscriptions(urls, total)) => {
+                    remove_unsubscribed(&mut self.videos, &urls);
+                    refresh.total = total;
+                },
+                Ok(Refreshed::Failed(e)) => {
+                    self.refresh = None;
+                    return debug(&e)
+                },
+                Ok(Refreshed::Feed(url, result)) => {
+                    refresh.done += 1;
+                    refresh.name = fetched_name(&url, &result);
+                    received = true;
+                    if refresh.auto {
+                        refresh.new.extend(get_new_videos(&self.videos, &url, &result));
+                        self.pending.push((url, result));
+                    } else {
+                        refresh.new.extend(merge_fetched(&mut self.videos, url, result, &self.app_config));
+                    }
+                },
+                Err(mpsc::TryRecvError::Empty) => break false,
+                Err(mpsc::TryRecvError::Disconnected) => break true,
+            }
+        };
+        if !finished {
+            if !refresh.auto && received {
+                self.redraw();
+            }
+            return
+        }
+        let new = std::mem::take(&mut refresh.new);
+        let auto = refresh.auto;
+        self.refresh = None;
+        announce_new_videos(&new, &self.app_config);
+        if !auto {
+            save_videos(&mut self.videos, &self.app_config);
+            self.fetch_durations_in_background();
+            self.show_new(new);
+        } else if new.is_empty() {
+            self.merge_pending();
+        } else {
+            debug(&format!("{} new videos, press R to merge", new.len()));
+            self.pending_new = new;
+        }
+    }
+
+    fn fetch_durations_in_background(&mut self) {
+        let missing = missing_durations(&self.videos);
+        if !self.app_config.fetch_durations || self.durations.is_some() || missing.is_empty() {
+            return
+        }
+        let app_config = self.app_config.clone();
+        let (sender, receiver) = mpsc::channel();
+        self.durations = Some(receiver);
+        runtime().spawn(async move {
+            let _res = sender.send(get_durations(&missing, &app_config).await);
+        });
+    }
+
+    fn receive_durations(&mut self) {
+        let durations = match self.durations.as_ref().map(|receiver| receiver.try_recv()) {
+            Some(Ok(durations)) => durations,
+            Some(Err(mpsc::TryRecvError::Empty)) | None => return,
+            Some(Err(mpsc::TryRecvError::Disconnected)) => vec![],
+        };
+        self.durations = None;
+        if !durations.is_empty() {
+            set_durations(&mut self.videos, durations);
+            save_videos(&mut self.videos, &self.app_config);
+            self.redraw();
+        }
+    }
+
+    // redraws the list keeping the current page and selection
+    fn redraw(&mut self) {
+        let i = self.i;
+        self.refresh_toshow(self.start + self.n);
+        self.clear_and_print_videos();
+        self.i = min(i, self.toshow.len().saturating_sub(1));
+    }
+
+    fn show_new(&mut self, new: Vec<Video>) {
+        let channels = new.iter().map(|video| video.channel.as_str()).collect::<HashSet<&str>>().len();
+        let message = format!("{} new videos from {} channels", new.len(), channels);
+        self.new_urls = new.into_iter().map(|video| video.url).collect();
+        self.redraw();
+        debug(&message);
+    }
+
+    // "line 37/480 (page 3/12)", lines are counted from the newest video
+    fn position(&self) -> String {
+        let offset = if self.app_config.newest_first { self.i } else { self.toshow.len().saturating_sub(self.i + 1) };
+        let line = min(self.start + offset + 1, std::cmp::max(self.total_videos, 1));
+        let pages = std::cmp::max(self.total_videos.div_ceil(self.n), 1);
+        format!("line {}/{} (page {}/{})", line, self.total_videos, self.start / self.n + 1, pages)
+    }
+
+    fn status(&self) -> String {
+        let mut parts = vec![];
+        if !self.filter.is_empty() {
+            parts.push(format!("filter: {}", self.filter));
+        }
+        if let Some(group) = &self.group {
+            parts.push(format!("group: {}", group));
+        }
+        parts.push(format!("{} unread", self.unread));
+        if let Some(refresh) = &self.refresh {
+            parts.push(format!("fetching {}/{} channels... {}", refresh.done, refresh.total, refresh.name));
+        }
+        if self.durations.is_some() {
+            parts.push("fetching durations".to_string());
+        }
+        if !self.pending_new.is_empty() {
+            parts.push(format!("{} new (R to merge)", self.pending_new.len()));
+        }
+        if let Some(left) = remaining_budget(&self.state, &self.app_config) {
+            parts.push(format!("{}m left today", left.max(0)));
+        }
+        if let Some(fetched) = self.videos.fetched.values().max() {
+            parts.push(format!("refreshed {} ago", format_age(now_timestamp() - fetched)));
+        }
+        if !self.toshow.is_empty() {
+            parts.push(self.position());
+        }
+        parts.join(" | ")
+    }
+
+    // draws the selected video in the preview pane when the selection changed
+    fn print_preview(&mut self) {
+        let video = match self.toshow.get(self.i) {
+            Some(video) if self.previewed.as_ref() != Some(&video.url) => video,
+            _ => return,
+        };
+        let cols = get_cols();
+        let list_cols = list_cols(&self.app_config);
+        let bottom = preview_lines(&self.app_config);
+        if bottom == 0 && list_cols == cols {
+            return
+        }
+        clear_thumbnails(&self.app_config);
+        let thumbnail = thumbnail_protocol(&self.app_config)
+            .and_then(|protocol| thumbnail_path(video, &self.app_config).map(|path| (protocol, path)));
+        if bottom > 0 {
+            let top = get_lines().saturating_sub(bottom);
+            let image_cols = thumbnail.as_ref().map(|_| min(thumbnail_cols(bottom - 1), cols / 3)).unwrap_or(0);
+            let x = if image_cols > 0 { image_cols + 2 } else { 1 };
+            let width = cols.saturating_sub(x + 1);
+            let lines = preview(video, width, &self.theme);
+            move_cursor_to(top, 0);
+            clear_to_end_of_line();
+            print!("{}", colorize(&self.theme.date, &"─".repeat(cols.saturating_sub(1))));
+            for row in 1..bottom {
+                move_cursor_to(top + row, 0);
+                clear_to_end_of_line();
+                move_cursor_to(top + row, x);
+                print!("{}{}", truncate_visible(lines.get(row - 1).map(|l| l.as_str()).unwrap_or(""), width), reset());
+            }
+            if let Some((protocol, path)) = &thumbnail {
+                draw_thumbnail(path, protocol, top + 1, 1, image_cols, bottom - 1);
+            }
+        } else {
+            let width = cols.saturating_sub(list_cols + 3);
+            let image_rows = thumbnail.as_ref().map(|_| min(thumbnail_rows(width), get_lines() / 2)).unwrap_or(0);
+            let lines = preview(video, width, &self.theme);
+            for row in 0..get_lines() {
+                let line = if row < image_rows { "" } else { lines.get(row - image_rows).map(|l| l.as_str()).unwrap_or("") };
+                move_cursor_to(row, list_cols);
+                clear_to_end_of_line();
+                print!("{} {}{}", colorize(&self.theme.date, "│"), truncate_visible(line, width), reset());
+            }
+            if let Some((protocol, path)) = &thumbnail {
+                draw_thumbnail(path, protocol, 0, list_cols + 2, width, image_rows);
+            }
+        }
+        self.previewed = Some(video.url.to_string());
+        io::stdout().flush().unwrap();
+    }
+
+    fn cycle_preview_pane(&mut self) {
+        self.app_config.preview_pane = match self.app_config.preview_pane.as_str() {
+            "" => "right",
+            "right" => "bottom",
+            _ => "",
+        }.to_string();
+        self.resize();
+        debug(&format!("preview pane: {}", if self.app_config.preview_pane.is_empty() { "off" } else { &self.app_config.preview_pane }));
+    }
+
+    // the last message on the left, the status on the right when there is room for both
+    fn print_status_bar(&self) {
+        let message = MESSAGE.with(|message| message.borrow().to_string());
+        let status = self.status();
+        let cols = get_cols();
+        move_to_bottom();
+        clear_to_end_of_line();
+        print!("{}{}", truncate_visible(&message, cols.saturating_sub(1)), reset());
+        if message.chars().count() + status.chars().count() + 2 < cols {
+            move_cursor_to(get_lines(), cols - status.chars().count() - 1);
+            print!("{}", colorize(&self.theme.date, &status));
+        }
+        io::stdout().flush().unwrap();
+    }
+
+    fn remember_position(&mut self) {
+        self.previous_position = self.toshow.get(self.i).map(|video| video.url.to_string());
+    }
+
+    // shows the page of a video and selects it, false when it is not in the list
+    fn go_to_video(&mut self, url: &str) -> bool {
+        let start = match self.listed_urls.iter().position(|listed| listed == url) {
+            Some(position) => position / self.n * self.n,
+            None => return false,
+        };
+        if start != self.start || !self.toshow.iter().any(|video| video.url == url) {
+            self.start = start;
+            self.show_page();
+        }
+        match self.toshow.iter().position(|video| video.url == url) {
+            Some(i) => {
+                self.i = jump(self.i, i);
+                true
+            },
+            None => false,
+        }
+    }
+
+    fn set_mark(&mut self) {
+        debug(&"mark (a-z): ".to_string());
+        match (read_key(), self.toshow.get(self.i)) {
+            (Some(Char(c)), Some(video)) if c.is_ascii_lowercase() => {
+                self.marks.insert(c, video.url.to_string());
+                debug(&format!("mark {} set", c));
+            },
+            _ => debug(&"".to_string()),
+        }
+    }
+
+    // '{a-z} goes to a mark, '' back to the video selected before the last jump
+    fn jump_to_mark(&mut self) {
+        debug(&"jump to mark (a-z, '): ".to_string());
+        let url = match read_key() {
+            Some(Char('\'')) => self.previous_position.clone(),
+            Some(Char(c)) if c.is_ascii_lowercase() => self.marks.get(&c).cloned(),
+            _ => return debug(&"".to_string()),
+        };
+        let current = self.toshow.get(self.i).map(|video| video.url.to_string());
+        match url {
+            Some(url) if self.go_to_video(&url) => {
+                self.previous_position = current;
+                debug(&"".to_string());
+            },
+            Some(_) => debug(&"the video of this mark is not in the list".to_string()),
+            None => debug(&"mark not set".to_string()),
+        }
+    }
+
+    fn jump_to_count(&mut self) {
+        match self.count.parse::<usize>() {
+            Ok(number) if number >= 1 && number <= self.toshow.len() => {
+                self.remember_position();
+                self.i = jump(self.i, number - 1);
+            },
+            _ => debug(&format!("no video {} on this page", self.count)),
+        }
+    }
+
+    fn quit(&mut self) {
+        if self.refresh.is_some() {
+            save_videos(&mut self.videos, &self.app_config);
+        }
+        quit(&self.app_config);
+    }
+
+    // restores the terminal until the shell resumes the process with fg
+    fn suspend(&mut self) {
+        quit(&self.app_config);
+        unsafe {
+            libc::raise(libc::SIGTSTP);
+        }
+        push_title();
+        if !self.app_config.inline {
+            smcup();
+        }
+        hide_cursor();
+        self.resize();
+    }
+
+    fn resize(&mut self) {
+        self.n = page_size(&self.app_config);
+        self.redraw();
+    }
+
+    fn first_page(&mut self) {
+        self.n = page_size(&self.app_config);
+        self.refresh_toshow(self.n);
+    }
+
+    fn play_current(&mut self) {
+        if self.i < self.toshow.len() {
+            let minutes = self.toshow[self.i].duration.map(|seconds| seconds.div_ceil(60) as i64).unwrap_or(0);
+            let warning = match remaining_budget(&self.state, &self.app_config) {
+                Some(left) if left <= 0 => Some("daily viewing budget exceeded".to_string()),
+                Some(left) if minutes > left => Some(format!("this video lasts {} minutes, {} left in the daily viewing budget", minutes, left)),
+                _ => None,
+            };
+            if let Some(warning) = warning {
+                debug(&format!("{}, play anyway ? [y/N]", warning));
+                match read_key() {
+                    Some(Char('y')) => (),
+                    _ => return debug(&"".to_string()),
+                }
+            }
+            let seconds = play(&self.toshow[self.i], &self.app_config);
+            if self.app_config.daily_budget_minutes > 0 {
+                *self.state.watch_time.entry(today()).or_insert(0) += seconds;
+                save_state(&mut self.state, &self.app_config);
+            }
+            if self.app_config.inline {
+                println!();
+                println!("played {} {}", self.toshow[self.i].channel, self.toshow[self.i].title);
+                scroll_to_scrollback();
+            }
+            if let Some(Some(id)) = get_id(&self.toshow[self.i]) {
+                if self.state.watched.insert(id) {
+                    save_state(&mut self.state, &self.app_config);
+                }
+            }
+            let i = self.i;
+            self.soft_reload();
+            self.i = min(i, self.toshow.len());
+        }
+    }
+
+    fn open_current(&mut self) {
+        if self.app_config.restricted_profile.is_some() {
+            return debug(&"disabled in restricted profile".to_string())
+        }
+        if self.i < self.toshow.len() {
+            let url = &self.toshow[self.i].url;
+            debug(&format!("opening {}", &url));
+            let _res = webbrowser::open(url);
+        }
+    }
+
+
+    fn toggle_mark(&mut self) {
+        if self.i < self.toshow.len() {
+            let url = self.toshow[self.i].url.to_string();
+            match self.marked.iter().position(|u| *u == url) {
+                Some(pos) => { self.marked.remove(pos); },
+                None => self.marked.push(url),
+            }
+            self.clear_and_print_videos();
+        }
+    }
+
+    // marks the videos of the list (all its pages), or every cached video, as watched
+    fn mark_all_read(&mut self, all: bool) {
+        if !self.writable() {
+            return self.clear_and_print_videos()
+        }
+        let listed = self.listed_urls.iter().collect::<HashSet<&String>>();
+        let watched = &self.state.watched;
+        let ids = self.videos.videos.iter()
+            .filter(|video| all || listed.contains(&video.url))
+            .flat_map(|video| match get_id(video) {
+                Some(Some(id)) if !watched.contains(&id) => Some(id),
+                _ => None,
+            }).collect::<HashSet<String>>();
+        self.clear_and_print_videos();
+        if ids.is_empty() {
+            return debug(&"no unwatched videos".to_string())
+        }
+        debug(&format!("mark {} videos as watched ? [y/N]", ids.len()));
+        match read_key() {
+            Some(Char('y')) => (),
+            _ => return debug(&"".to_string()),
+        }
+        let count = ids.len();
+        self.state.watched.extend(ids);
+        save_state(&mut self.state, &self.app_config);
+        self.redraw();
+        debug(&format!("{} videos marked as watched", count));
+    }
+
+    fn writable(&mut self) -> bool {
+        if self.app_config.read_only {
+            debug(&"disabled in read-only mode".to_string());
+        }
+        !self.app_config.read_only && self.unlocked()
+    }
+
+    // asks for the restricted profile pin, if any
+    fn unlocked(&mut self) -> bool {
+        let pin = match &self.app_config.restricted_profile {
+            Some(_) if self.command_unlocked => return true,
+            Some(profile) => profile.pin.to_string(),
+            None => return true,
+        };
+        move_to_bottom();
+        clear_to_end_of_line();
+        print!("pin: ");
+        io::stdout().flush().unwrap();
+        if read_pin() == pin {
+            debug(&"".to_string());
+            true
+        } else {
+            debug(&"wrong pin".to_string());
+            false
+        }
+    }
+
+    fn export_batch(&mut self) {
+        if !self.writable() {
+            return
+        }
+        let videos = if self.marked.is_empty() {
+            filter_videos(&self.videos.videos, &self.filter, self.app_config.case_sensitive_search)
+        } else {
+            self.videos.videos.iter().filter(|v| self.marked.contains(&v.url)).cloned().collect()
+        };
+        match write_batch_file(&videos, &self.app_config) {
+            Ok(count) => {
+                if self.app_config.batch_download {
+                    clear();
+                    move_cursor(0);
+                    download_batch_file(&self.app_config);
+                    self.marked.clear();
+                    self.clear_and_print_videos();
+                }
+                debug(&format!("wrote {} urls to {}", count, self.app_config.batch_file_path));
+            },
+            Err(e) => debug(&format!("failed writing {}: {}", self.app_config.batch_file_path, e)),
+        }
+    }
+
+    fn find(&mut self, s: String) -> usize {
+        let matcher = text_matcher(&s, self.app_config.case_sensitive_search);
+        for (i, video) in self.toshow.iter().enumerate() {
+            if matches_text(video, &matcher) {
+                return i;
+            }
+        }
+        0
+    }
+
+    fn input_with_prefix(&mut self, start_symbol: &str) -> String {
+        read_line_with_history(start_symbol, &[])
+    }
+
+    fn input_with_history(&mut self, prefix: &str) -> String {
+        let history = self.state.history.entry(prefix.to_string()).or_default();
+        let line = read_line_with_history(prefix, history);
+        if !line.trim().is_empty() {
+            history.retain(|previous| *previous != line);
+            history.push(line.to_string());
+            let excess = history.len().saturating_sub(MAX_HISTORY);
+            history.drain(..excess);
+            save_state(&mut self.state, &self.app_config);
+        }
+        line
+    }
+
+    fn search(&mut self) {
+        let s = self.input_with_history("/");
+        self.last_search = Some(s.to_string()).filter(|s| !s.is_empty());
+        self.i = self.find(s);
+        self.clear_and_print_videos()
+    }
+
+    // selects the next (or previous) video matching the last search on the page, wrapping around
+    fn next_match(&mut self, forward: bool) {
+        let s = match &self.last_search {
+            Some(s) => s,
+            None => return debug(&"no search, use / first".to_string()),
+        };
+        let matcher = text_matcher(s, self.app_config.case_sensitive_search);
+        let n = self.toshow.len();
+        let i = min(self.i, n.saturating_sub(1));
+        let found = (1..=n).map(|step| if forward { (i + step) % n } else { (i + n - step) % n })
+            .find(|&j| matches_text(&self.toshow[j], &matcher));
+        match found {
+            Some(j) => {
+                self.remember_position();
+                self.i = jump(self.i, j);
+            },
+            None => debug(&format!("no match for {}", s)),
+        }
+    }
+
+    fn filter(&mut self) {
+        let s = self.input_with_history("|");
+        self.filter = s;
+        self.move_page(0);
+        self.clear_and_print_videos()
+    }
+
+    // the pin is asked once, before the command is typed
+    fn command(&mut self) {
+        if !self.unlocked() {
+            return
+        }
+        self.command_unlocked = true;
+        self.run_command();
+        self.command_unlocked = false;
+    }
+
+    fn run_command(&mut self) {
+        let s = self.input_with_history(":");
+        let s = s.trim().splitn(2, ' ').collect::<Vec<&str>>();
+	hide_cursor();
+        clear();
+        if s[0] == "mark-all-read" {
+            return self.mark_all_read(s.get(1) == Some(&"all"))
+        }
+        if s.len() == 2 {
+            match s[0] {
+                "o" => play_id(&s[1].to_string(), &self.app_config),
+                "subscribe" => return self.subscribe(s[1]),
+                "find-channel" => return self.find_channel(s[1]),
+                "unmute" => return self.unmute(s[1]),
+                "mix" => return self.set_mix(s[1]),
+                "shuffle" => return self.shuffle_mix(s[1]),
+                "mark-watched" | "unmark" => {
+                    if !self.writable() {
+                        return self.clear_and_print_videos()
+                    }
+                    let count = set_watched_matching(&mut self.state, &self.videos.videos, s[1], s[0] == "mark-watched");
+                    save_state(&mut self.state, &self.app_config);
+                    self.clear_and_print_videos();
+                    return debug(&format!("{} videos updated", count))
+                },
+                "presentation" => {
+                    self.app_config.presentation = s[1] == "on";
+                    self.app_config.read_only = self.app_config.safe_mode || self.app_config.presentation;
+                    self.clear_and_print_videos();
+                    return debug(&format!("presentation mode {}{}", if self.app_config.presentation { "on" } else { "off" },
+                        if self.app_config.safe_mode { ", still read-only in safe mode" } else { "" }))
+                },
+                _ => ()
             }
         }
-        self.toshow = to_show_videos(&mut self.videos.videos, self.start, self.start + self.n, &self.filter);
-        self.i = 0;
         self.clear_and_print_videos()
     }
 
-    fn next_page(&mut self) {
-        self.move_page(-1);
+    fn find_channel(&mut self, query: &str) {
+        if self.app_config.invidious_instances.is_empty() {
+            self.soft_reload();
+            return debug(&"channel search requires invidious_instances in configuration".to_string())
+        }
+        debug(&format!("searching channels matching {}...", query));
+        let results = runtime().block_on(search_channels(query, &self.app_config));
+        clear();
+        move_cursor(0);
+        for (i, (name, id, subscribers)) in results.iter().enumerate().take(get_lines() - 1) {
+            println!("  {:>2} {} {} ({} subscribers)", i + 1, colorize(&self.theme.channel, name), id, subscribers);
+        }
+        let choice = self.input_with_prefix("subscribe to number (empty to cancel): ");
+        hide_cursor();
+        match choice.trim().parse::<usize>() {
+            Ok(n) if n >= 1 && n <= results.len() => self.subscribe(&results[n - 1].1),
+            _ => self.soft_reload(),
+        }
     }
 
-    fn previous_page(&mut self) {
-        self.move_page(1);
+    fn unsubscribe_current(&mut self) {
+        if self.i >= self.toshow.len() || !self.writable() {
+            return
+        }
+        let video = self.toshow[self.i].clone();
+        if video.feed.is_empty() {
+            return debug(&"channel source unknown, press R to refresh first".to_string())
+        }
+        let answer = self.input_with_prefix(&format!("unsubscribe from {} ? [y/N] ", video.channel));
+        hide_cursor();
+        if answer.trim() != "y" {
+            return self.clear_and_print_videos()
+        }
+        if let Some(path) = subscriptions_path() {
+            if let Ok(xml) = fs::read_to_string(&path) {
+                let mut outlines = get_opml_outlines(xml.as_str());
+                let count = outlines.len();
+                outlines.retain(|(_, url)| *url != video.feed);
+                if outlines.len() != count {
+                    if let Err(e) = write_atomically(&path, to_opml(&outlines).as_bytes()) {
+                        self.soft_reload();
+                        return debug(&format!("failed writing {}: {}", path, e))
+                    }
+                }
+            }
+        }
+        if let Err(e) = remove_feed_from_config(&video.feed) {
+            self.soft_reload();
+            return debug(&format!("failed to update configuration: {}", e))
+        }
+        if let Ok(config) = try_load_config() {
+            self.reload_feed_lists(config);
+        }
+        self.videos.videos.retain(|v| v.feed != video.feed);
+        save_videos(&mut self.videos, &self.app_config);
+        self.soft_reload();
+        debug(&format!("unsubscribed from {}", video.channel));
     }
 
-    fn soft_reload(&mut self) {
-        self.move_page(0);
+    fn subscribe_prompt(&mut self) {
+        let s = self.input_with_prefix(":subscribe ");
+        hide_cursor();
+        self.subscribe(s.trim());
     }
 
-    fn hard_reload(&mut self) {
-        debug(&"updating video list...".to_string());
-        self.videos = load(true, &self.app_config).unwrap();
-        debug(&"".to_string());
+    // resolves the channel and fetches its feed in the background, see receive_subscription
+    fn subscribe(&mut self, input: &str) {
+        if !self.writable() {
+            return self.clear_and_print_videos()
+        }
         self.soft_reload();
+        if self.subscription.is_some() {
+            return debug(&"already subscribing to a channel".to_string())
+        }
+        debug(&format!("resolving {}...", input));
+        let input = input.to_string();
+        let app_config = self.app_config.clone();
+        let (sender, receiver) = mpsc::channel();
+        self.subscription = Some(receiver);
+        runtime().spawn(async move {
+            let result = match resolve_channel_id(&input, &app_config).await {
+                Some(id) if app_config.channel_ids.contains(&id) => Err(format!("already subscribed to {}", id)),
+                Some(id) => {
+                    let url = format!("https://www.youtube.com/feeds/videos.xml?channel_id={}", id);
+                    Ok((id, get_feed_videos(&url, &app_config).await))
+                },
+                None => Err(format!("could not find a channel for {}", input)),
+            };
+            let _res = sender.send(result);
+        });
     }
 
-    fn first_page(&mut self) {
-        self.n = get_lines();
-        self.toshow = to_show_videos(&mut self.videos.videos, self.start, self.n, &self.filter);
+    fn receive_subscription(&mut self) {
+        let result = match self.subscription.as_ref().map(|receiver| receiver.try_recv()) {
+            Some(Ok(result)) => result,
+            Some(Err(mpsc::TryRecvError::Empty)) | None => return,
+            Some(Err(mpsc::TryRecvError::Disconnected)) => Err("could not subscribe".to_string()),
+        };
+        self.subscription = None;
+        let (channel_id, videos) = match result {
+            Ok(subscription) => subscription,
+            Err(e) => return debug(&e),
+        };
+        if let Err(e) = add_channel_id_to_config(&channel_id) {
+            return debug(&format!("failed to update configuration: {}", e))
+        }
+        self.app_config.channel_ids.push(channel_id.to_string());
+        let message = format!("subscribed to {} ({} videos)", videos.first().map(|v| v.channel.as_str()).unwrap_or(&channel_id), videos.len());
+        self.videos.videos.extend(videos);
+        save_videos(&mut self.videos, &self.app_config);
+        self.redraw();
+        debug(&message);
     }
 
-    fn play_current(&mut self) {
-        if self.i < self.toshow.len() {
-            play(&self.toshow[self.i], &self.app_config);
-            self.clear_and_print_videos();
+    // fetches the statistics of the selected video from invidious_instances in the background
+    fn update_statistics(&mut self) {
+        if self.statistics.is_some() {
+            return
         }
+        let video = self.toshow[self.i].clone();
+        let app_config = self.app_config.clone();
+        let (sender, receiver) = mpsc::channel();
+        self.statistics = Some(receiver);
+        runtime().spawn(async move {
+            if let Some(statistics) = get_video_statistics(&video, &app_config).await {
+                let _res = sender.send((video.url, statistics));
+            }
+        });
     }
 
-    fn open_current(&mut self) {
-        if self.i < self.toshow.len() {
-            let url = &self.toshow[self.i].url;
-            debug(&format!("opening {}", &url));
-            let _res = webbrowser::open(&url);
+    // true when statistics were received and stored
+    fn receive_statistics(&mut self) -> bool {
+        let (url, (views, likes, subscribers)) = match self.statistics.as_ref().map(|receiver| receiver.try_recv()) {
+            Some(Ok(statistics)) => statistics,
+            Some(Err(mpsc::TryRecvError::Disconnected)) => {
+                self.statistics = None;
+                return false
+            },
+            Some(Err(mpsc::TryRecvError::Empty)) | None => return false,
+        };
+        self.statistics = None;
+        for video in self.toshow.iter_mut().chain(self.videos.videos.iter_mut()).filter(|video| video.url == url) {
+            video.views = views.or(video.views);
+            video.likes = likes.or(video.likes);
+            video.subscribers = subscribers.clone().or(video.subscribers.take());
         }
+        save_videos(&mut self.videos, &self.app_config);
+        true
     }
 
-
-    fn find(&mut self, s: String) -> usize {
-        for (i, video) in self.toshow.iter().enumerate() {
-            if video.channel.contains(s.as_str()) || video.title.contains(s.as_str()) {
-                return i;
+    // scrollable information of a video, below its thumbnail, redrawn when its statistics
+    // or its thumbnail arrive
+    fn show_info(&mut self, v: &Video) {
+        let mut v = v.clone();
+        let image_cols = min(get_cols() / 2, 60);
+        let urls = extract_urls(&v.description);
+        let height = get_lines();
+        let mut offset = 0;
+        let mut count = String::new();
+        let mut message = String::new();
+        loop {
+            let thumbnail = thumbnail_protocol(&self.app_config)
+                .and_then(|protocol| thumbnail_path(&v, &self.app_config).map(|path| (protocol, path)));
+            let image_rows = thumbnail.as_ref().map(|_| min(thumbnail_rows(image_cols), get_lines() / 2)).unwrap_or(0);
+            let mut lines = vec!["".to_string(); if image_rows > 0 { image_rows + 1 } else { 0 }];
+            lines.extend(info_lines(&v, get_cols().saturating_sub(1), &self.app_config, &self.theme));
+            let last = lines.len().saturating_sub(height);
+            clear();
+            clear_thumbnails(&self.app_config);
+            for (row, line) in lines.iter().skip(offset).take(height).enumerate() {
+                move_cursor_to(row, 0);
+                print!("{}{}", truncate_visible(line, get_cols().saturating_sub(1)), reset());
+            }
+            if let (0, Some((protocol, path))) = (offset, &thumbnail) {
+                draw_thumbnail(path, protocol, 0, 0, image_cols, image_rows);
+            }
+            let help = if urls.is_empty() { "" } else { ", <n>o to open link n, <n>y to copy it" };
+            if !count.is_empty() {
+                debug(&format!("link {}", count));
+            } else if !message.is_empty() {
+                debug(&std::mem::take(&mut message));
+            } else if last > 0 {
+                debug(&format!("{}% (j, k, space and b to scroll, q to go back{})", (offset + height) * 100 / lines.len(), help));
+            } else if !urls.is_empty() {
+                debug(&format!("q to go back{}", help));
+            } else {
+                io::stdout().flush().unwrap();
+            }
+            let url = count.parse::<usize>().ok().and_then(|n| n.checked_sub(1)).and_then(|i| urls.get(i));
+            // None when the statistics or a thumbnail arrived before a key
+            let key = loop {
+                if self.statistics.is_none() && !thumbnails_downloading() {
+                    break Some(read_key())
+                }
+                if let Some(key) = poll_key(Some(Duration::from_millis(200))) {
+                    break Some(Some(key))
+                }
+                if INTERRUPTED.load(Ordering::SeqCst) {
+                    break Some(None)
+                }
+                if self.receive_statistics() || THUMBNAIL_DOWNLOADED.swap(false, Ordering::SeqCst) {
+                    break None
+                }
+            };
+            let key = match key {
+                Some(key) => key,
+                None => {
+                    if let Some(video) = self.videos.videos.iter().find(|video| video.url == v.url) {
+                        v = video.clone();
+                    }
+                    continue
+                },
+            };
+            match key {
+                Some(Char(c)) if c.is_ascii_digit() && !urls.is_empty() => {
+                    count.push(c);
+                    continue;
+                },
+                Some(Char('o')) if self.app_config.restricted_profile.is_some() => message = "disabled in restricted profile".to_string(),
+                Some(Char('o')) => match url {
+                    Some(url) => {
+                        message = format!("opening {}", url);
+                        let _res = webbrowser::open(url);
+                    },
+                    None => message = "type the number of a link, then o".to_string(),
+                },
+                Some(Char('y')) => match url {
+                    Some(url) => {
+                        copy_to_clipboard(url);
+                        message = format!("copied {}", url);
+                    },
+                    None => message = "type the number of a link, then y".to_string(),
+                },
+                Some(Char('j')) | Some(Down) => offset = min(offset + 1, last),
+                Some(Char('k')) | Some(Up) => offset = offset.saturating_sub(1),
+                Some(Char(' ')) | Some(Key::PageDown) => offset = min(offset + height, last),
+                Some(Char('b')) | Some(Key::PageUp) => offset = offset.saturating_sub(height),
+                Some(Char('g')) => offset = 0,
+                Some(Char('G')) => offset = last,
+                _ => break,
             }
+            count.clear();
         }
-        0
+        clear_thumbnails(&self.app_config);
+        MESSAGE.with(|message| message.borrow_mut().clear());
     }
 
-    fn input_with_prefix(&mut self, start_symbol: &str) -> String {
-        move_to_bottom();
-        print!("{}", start_symbol);
-        io::stdout().flush().unwrap();
-        let input = input();
-        input.read_line().unwrap()
+    fn info(&mut self) {
+        if self.i < self.toshow.len() {
+            if !self.app_config.invidious_instances.is_empty() {
+                self.update_statistics();
+            }
+            let video = self.toshow[self.i].clone();
+            self.show_info(&video);
+            self.redraw();
+        }
     }
 
-    fn search(&mut self) {
-        let s = self.input_with_prefix("/");
-        self.i = self.find(s);
-        self.clear_and_print_videos()
+    fn drill_down(&mut self) {
+        match self.saved_view.take() {
+            Some((filter, start, i)) => {
+                self.filter = filter;
+                self.start = start;
+                self.show_page();
+                self.i = i;
+            },
+            None => {
+                if self.i >= self.toshow.len() {
+                    return
+                }
+                self.saved_view = Some((self.filter.to_string(), self.start, self.i));
+                self.filter = format!("channel:{}", self.toshow[self.i].channel);
+                self.soft_reload();
+                debug(&"press D to go back to the list".to_string());
+            }
+        }
     }
 
-    fn filter(&mut self) {
-        let s = self.input_with_prefix("|");
-        self.filter = s;
+    fn toggle_channel_filter(&mut self) {
+        if self.filter.starts_with("channel:") {
+            self.filter = "".to_string();
+        } else if self.i < self.toshow.len() {
+            self.filter = format!("channel:{}", self.toshow[self.i].channel);
+        } else {
+            return
+        }
+        self.i = 0;
         self.move_page(0);
         self.clear_and_print_videos()
     }
 
-    fn command(&mut self) {
-        let s = self.input_with_prefix(":");
-        let s = s.split_whitespace().collect::<Vec<&str>>();
-	hide_cursor();
-        clear();
-        if s.len() == 2 {
-            match s[0] {
-                "o" => play_id(&s[1].to_string(), &self.app_config),
-                _ => ()
+    // the videos of the page in a grid of thumbnails, newest first like a channel page
+    fn grid(&mut self) {
+        let protocol = thumbnail_protocol(&self.app_config);
+        let mut selected = 0;
+        let mut offset = 0;
+        let mut draw = true;
+        loop {
+            let order = if self.app_config.newest_first {
+                (0..self.toshow.len()).collect::<Vec<usize>>()
+            } else {
+                (0..self.toshow.len()).rev().collect::<Vec<usize>>()
+            };
+            if order.is_empty() {
+                break
+            }
+            let cols = get_cols();
+            let columns = std::cmp::max(cols / GRID_CELL_COLS, 1);
+            let cell_cols = cols / columns;
+            let text_cols = cell_cols.saturating_sub(2);
+            let image_rows = if protocol.is_some() { thumbnail_rows(text_cols) } else { 0 };
+            let cell_rows = image_rows + 4;
+            let visible_rows = std::cmp::max(get_lines() / cell_rows, 1);
+            selected = min(selected, order.len() - 1);
+            let row = selected / columns;
+            if row < offset || row >= offset + visible_rows {
+                offset = if row < offset { row } else { row + 1 - visible_rows };
+                draw = true;
+            }
+            let cells = offset * columns..min((offset + visible_rows) * columns, order.len());
+            if draw {
+                clear();
+                clear_thumbnails(&self.app_config);
+                if let Some(protocol) = protocol {
+                    for cell in cells.clone() {
+                        if let Some(path) = thumbnail_path(&self.toshow[order[cell]], &self.app_config) {
+                            draw_thumbnail(&path, protocol, (cell / columns - offset) * cell_rows, cell % columns * cell_cols + 1, text_cols, image_rows);
+                        }
+                    }
+                }
+                draw = false;
+            }
+            for cell in cells {
+                let video = &self.toshow[order[cell]];
+                let (y, x) = ((cell / columns - offset) * cell_rows + image_rows, cell % columns * cell_cols + 1);
+                let title_color = if cell == selected { &self.theme.selector } else { "" };
+                let mut lines = wrap(&video.title, text_cols).into_iter().map(|line| colorize(title_color, &line)).take(2).collect::<Vec<String>>();
+                lines.resize(2, "".to_string());
+                let age = parse_timestamp(&video.published).map(|t| format_age(now_timestamp() - t)).unwrap_or("".to_string());
+                lines.push(format!("{} {}", colorize(&self.theme.date, &age), colorize(&self.theme.channel, &video.channel)));
+                for (i, line) in lines.iter().enumerate() {
+                    move_cursor_to(y + i, x);
+                    print!("{}", " ".repeat(text_cols));
+                    move_cursor_to(y + i, x);
+                    print!("{}{}", truncate_visible(line, text_cols), reset());
+                }
+            }
+            debug(&"hjkl to move, p to play, i for information, q to go back to the list".to_string());
+            let key = if protocol.is_some() && thumbnails_downloading() {
+                poll_key(Some(Duration::from_millis(200)))
+            } else {
+                read_key()
+            };
+            if INTERRUPTED.load(Ordering::SeqCst) {
+                break
+            }
+            if key.is_none() && THUMBNAIL_DOWNLOADED.swap(false, Ordering::SeqCst) {
+                draw = true;
+                continue
+            }
+            match key {
+                Some(Char('l')) | Some(Right) => selected = min(selected + 1, order.len() - 1),
+                Some(Char('h')) | Some(Left) => selected = selected.saturating_sub(1),
+                Some(Char('j')) | Some(Down) => selected = min(selected + columns, order.len() - 1),
+                Some(Char('k')) | Some(Up) => selected = selected.saturating_sub(columns),
+                Some(Char('\n')) | Some(Char('p')) => {
+                    self.i = order[selected];
+                    self.play_current();
+                    draw = true;
+                },
+                Some(Char('i')) => {
+                    let video = self.toshow[order[selected]].clone();
+                    self.show_info(&video);
+                    draw = true;
+                },
+                Some(Char('q')) | Some(Char('W')) | Some(Key::Esc) => {
+                    self.i = order[selected];
+                    break
+                },
+                _ => (),
             }
         }
-        self.clear_and_print_videos()
+        self.redraw();
     }
 
-    fn wait_key_press_and_soft_reload(&mut self) {
-        pause();
-        clear();
+    // "all" then the channels, with their number of unwatched videos
+    fn sidebar_channels(&self) -> Vec<(String, usize)> {
+        let allowed = self.app_config.restricted_profile.as_ref().map(|profile| &profile.allowed_channels);
+        let videos = self.videos.videos.iter().filter(|video| allowed.map(|a| in_channel_group(video, a)).unwrap_or(true)).cloned().collect();
+        let summaries = get_channel_summaries(&videos, &self.state);
+        let mut channels = vec![("all".to_string(), summaries.iter().map(|channel| channel.unread).sum())];
+        channels.extend(summaries.into_iter().map(|channel| (channel.name, channel.unread)));
+        channels
+    }
+
+    fn print_sidebar(&self) {
+        let width = sidebar_cols(&self.app_config);
+        if width == 0 {
+            return
+        }
+        let channels = self.sidebar_channels();
+        let lines = get_lines() - preview_lines(&self.app_config);
+        let offset = (self.sidebar_i + 1).saturating_sub(lines);
+        for row in 0..lines {
+            move_cursor_to(row, 0);
+            print!("{}", " ".repeat(width - 1));
+            move_cursor_to(row, 0);
+            if let Some((name, unread)) = channels.get(offset + row) {
+                let color = match offset + row == self.sidebar_i {
+                    true if self.sidebar_focus => &self.theme.selector,
+                    true => &self.theme.highlight,
+                    false => &self.theme.channel,
+                };
+                let count = unread.to_string();
+                let name = name.chars().take(width - count.len() - 4).collect::<String>();
+                let padding = " ".repeat(width - count.len() - 3 - name.chars().count());
+                print!(" {}{}{}", colorize(color, &name), padding, colorize(&self.theme.date, &count));
+            }
+            move_cursor_to(row, width - 1);
+            print!("{}", colorize(&self.theme.date, "│"));
+        }
+        io::stdout().flush().unwrap();
+    }
+
+    fn toggle_sidebar_focus(&mut self) {
+        if get_cols() < PANES_MIN_COLS {
+            return debug(&"the terminal is too narrow for the channel sidebar".to_string())
+        }
+        self.sidebar_focus = !self.sidebar_focus || !self.app_config.channel_sidebar;
+        if !self.app_config.channel_sidebar {
+            self.app_config.channel_sidebar = true;
+            self.redraw();
+        } else {
+            self.print_sidebar();
+        }
+    }
+
+    // shows the videos of the selected channel, or all of them
+    fn select_sidebar_channel(&mut self, i: usize) {
+        let channels = self.sidebar_channels();
+        self.sidebar_i = min(i, channels.len() - 1);
+        self.filter = if self.sidebar_i == 0 { "".to_string() } else { format!("channel:{}", channels[self.sidebar_i].0) };
         self.soft_reload();
     }
 
-    fn info(&mut self) {
-        if self.i < self.toshow.len() {
+    fn channels(&mut self) {
+        let allowed = self.app_config.restricted_profile.as_ref().map(|profile| &profile.allowed_channels);
+        let videos = self.videos.videos.iter().filter(|video| allowed.map(|a| in_channel_group(video, a)).unwrap_or(true)).cloned().collect();
+        let channels = get_channel_summaries(&videos, &self.state);
+        if channels.is_empty() {
+            return
+        }
+        let max = channels.iter().map(|c| c.name.chars().count()).max().unwrap_or(0);
+        let mut i = 0;
+        let mut offset = 0;
+        loop {
+            let lines = get_lines();
+            if i < offset {
+                offset = i;
+            } else if i >= offset + lines {
+                offset = i + 1 - lines;
+            }
             clear();
-            print_info(&self.toshow[self.i]);
-            self.wait_key_press_and_soft_reload()
+            move_cursor(0);
+            for channel in channels.iter().skip(offset).take(lines) {
+                println!("  {} {}{} {:>4} unread / {}",
+                         colorize(&self.theme.date, channel.last_upload.split("T").next().unwrap_or("")),
+                         colorize(&self.theme.channel, &channel.name),
+                         " ".repeat(max - channel.name.chars().count()), channel.unread, channel.count);
+            }
+            print_selector(i - offset, 0, &self.theme);
+            let key = read_key();
+            if INTERRUPTED.load(Ordering::SeqCst) {
+                break
+            }
+            match key {
+                Some(Char('j')) | Some(Down) => i = min(i + 1, channels.len() - 1),
+                Some(Char('k')) | Some(Up) => i = i.saturating_sub(1),
+                Some(Char('g')) => i = 0,
+                Some(Char('G')) => i = channels.len() - 1,
+                Some(Char('\n')) | Some(Char('p')) | Some(Right) => {
+                    self.filter = format!("channel:{}", channels[i].name);
+                    break;
+                },
+                Some(Char('q')) | Some(Char('C')) | Some(Left) | Some(Key::Esc) => break,
+                _ => (),
+            }
         }
+        self.soft_reload();
     }
 
+    // the help in a box over the list, j and k scroll it, any other key closes it
     fn help(&mut self) {
-        clear();
-        print_help();
-        self.wait_key_press_and_soft_reload()
+        let lines = help_lines();
+        let width = min(lines.iter().map(|line| line.chars().count()).max().unwrap_or(0) + 4, get_cols().saturating_sub(2));
+        let height = min(lines.len() + 2, get_lines().saturating_sub(2));
+        if width < 5 || height < 3 {
+            return
+        }
+        let (top, left) = ((get_lines() - height) / 2, (get_cols() - width) / 2);
+        let mut offset = 0;
+        clear_thumbnails(&self.app_config);
+        loop {
+            move_cursor_to(top, left);
+            print!("┌{}┐", "─".repeat(width - 2));
+            for row in 1..height - 1 {
+                let line = lines.get(offset + row - 1).map(|line| line.as_str()).unwrap_or("");
+                let line = truncate_visible(line, width - 4);
+                move_cursor_to(top + row, left);
+                print!("│ {}{} │", line, " ".repeat(width - 4 - line.chars().count()));
+            }
+            move_cursor_to(top + height - 1, left);
+            print!("└{}┘", "─".repeat(width - 2));
+            io::stdout().flush().unwrap();
+            match read_key() {
+                Some(Char('j')) | Some(Down) => offset = min(offset + 1, lines.len() + 2 - height),
+                Some(Char('k')) | Some(Up) => offset = offset.saturating_sub(1),
+                _ => break,
+            }
+        }
+        self.redraw();
     }
 
     fn download(&mut self, take: usize) {
-        self.hard_reload();
+        let progress = |done: usize, total: usize, name: &str| println!("fetching {}/{} channels... {}", done, total, name);
+        self.videos = load(true, true, &progress, &self.app_config).unwrap();
         for video in self.videos.videos.iter().rev().take(take) {
-            match get_id(video) {
-                Some(Some(id)) => {
-                    let path = format!("/tmp/{}.mp4", id);
-                    download_video(&path, &id, &self.app_config);
-                },
-                _ => (),
+            if let Some(Some(id)) = get_id(video) {
+                let path = format!("/tmp/{}.mp4", id);
+                download_video(&path, &id, &play_settings(Some(video), &self.app_config));
             }
         }
     }
 
+    fn subscribed_feeds(&self, app_config: &AppConfig) -> Vec<String> {
+        let xml = subscriptions_path().and_then(|path| fs::read_to_string(path).ok()).unwrap_or("<opml/>".to_string());
+        get_feed_urls(xml.as_str(), &app_config.channel_ids, app_config)
+    }
+
+    // the channels, playlists and feeds subscribed to in config
+    fn reload_feed_lists(&mut self, config: AppConfig) {
+        self.app_config.channel_ids = config.channel_ids;
+        self.app_config.playlist_ids = config.playlist_ids;
+        self.app_config.peertube_feeds = config.peertube_feeds;
+        self.app_config.odysee_channels = config.odysee_channels;
+        self.app_config.feeds = config.feeds;
+    }
+
+    fn check_subscriptions_changed(&mut self) {
+        let modified = (modified_time(&subscriptions_path()), modified_time(&config_path()));
+        if modified == self.subscriptions_modified || self.app_config.read_only {
+            return
+        }
+        let previous_feeds = self.subscribed_feeds(&self.app_config);
+        self.subscriptions_modified = modified;
+        // the previous configuration is kept until the file is fixed
+        let new_config = match try_load_config() {
+            Ok(config) => config,
+            Err(e) => return debug(&e),
+        };
+        let new_feeds = self.subscribed_feeds(&new_config).into_iter()
+            .filter(|feed| !previous_feeds.contains(feed))
+            .collect::<Vec<String>>();
+        if new_feeds.is_empty() {
+            return
+        }
+        let answer = self.input_with_prefix(&format!("{} new channels found in subscriptions, fetch them ? [y/N] ", new_feeds.len()));
+        hide_cursor();
+        if answer.trim() == "y" {
+            self.reload_feed_lists(new_config);
+            self.soft_reload();
+            debug(&format!("fetching {} channels...", new_feeds.len()));
+            return self.fetch_in_background(Some(new_feeds), false)
+        }
+        self.soft_reload();
+    }
+
+    // whether the cache was written more than cache_max_age_hours ago
+    fn cache_expired(&self) -> bool {
+        let max_age = Duration::from_secs(self.app_config.cache_max_age_hours * 3600);
+        self.app_config.cache_max_age_hours > 0 && modified_time(&Some(self.app_config.cache_path.to_string()))
+            .and_then(|modified| modified.elapsed().ok())
+            .map(|age| age > max_age)
+            .unwrap_or(false)
+    }
+
     fn run(&mut self) {
-        self.videos = load(false, &self.app_config).unwrap();
+        let expired = self.cache_expired();
+        self.videos = load(false, false, &no_progress, &self.app_config).unwrap();
+        self.subscriptions_modified = (modified_time(&subscriptions_path()), modified_time(&config_path()));
         self.start = 0;
         self.i = 0;
-        smcup();
+        push_title();
+        watch_signals();
+        quit_on_panic(&self.app_config);
+        if !self.app_config.inline {
+            smcup();
+        }
         self.first_page();
         self.clear_and_print_videos();
         hide_cursor();
+        if expired {
+            self.start_refresh(false);
+        }
+        self.fetch_durations_in_background();
         loop {
-            print_selector(self.i);
-            let input = input();
-            let result;
-            {
-                let _screen = RawScreen::into_raw_mode();
-                let mut stdin = input.read_sync();
-                result = stdin.next();
-            }
-            match result {
-                Some(key_event) => {
-                    match key_event {
-                        InputEvent::Keyboard(event) => {
-                            match event {
-                                Char('q') => {
-                                    quit();
-                                    break;
-                                },
-                                Char('j') | Char('l') | Down => self.i = jump(self.i, self.i + 1),
-                                Char('k') | Up => self.i = jump(self.i, if self.i > 0 { self.i - 1 } else { self.n - 1 }),
-                                Char('g') | Char('H') => self.i = jump(self.i, 0),
-                                Char('M') => self.i = jump(self.i, self.n / 2),
-                                Char('G') | Char('L') => self.i = jump(self.i, self.n - 1),
-                                Char('r') | Char('$') | Left => self.soft_reload(),
-                                Char('P') => self.previous_page(),
-                                Char('N') => self.next_page(),
-                                Char('R') => self.hard_reload(),
-                                Char('h') | Char('?') => self.help(),
-                                Char('i') | Right => self.info(),
-                                Char('p') | Char('\n') => self.play_current(),
-                                Char('o') => self.open_current(),
-                                Char('/') => self.search(),
-                                Char(':') => self.command(),
-                                Char('f') => self.filter(),
-                                _ => debug(&format!("key not supported (press h for help)")),
-                            }
-                        },
-                        _ => ()
-                    }
+            print_selector(video_row(self.i), list_left(), &self.theme);
+            self.print_preview();
+            self.print_status_bar();
+            let result = poll_key(Some(Duration::from_millis(500)));
+            if result.is_none() {
+                self.check_subscriptions_changed();
+                let interval = Duration::from_secs(self.app_config.auto_refresh_minutes * 60);
+                if self.app_config.auto_refresh_minutes > 0 && self.refresh.is_none() && self.pending.is_empty() && self.last_refresh.elapsed() >= interval {
+                    self.start_refresh(true);
+                }
+            }
+            self.receive_refresh();
+            self.receive_durations();
+            self.receive_subscription();
+            self.receive_statistics();
+            if RESIZED.swap(false, Ordering::SeqCst) {
+                self.resize();
+            }
+            if THUMBNAIL_DOWNLOADED.swap(false, Ordering::SeqCst) {
+                self.previewed = None;
+            }
+            if INTERRUPTED.load(Ordering::SeqCst) {
+                self.quit();
+                break;
+            }
+            let pressed = result.is_some();
+            if pressed {
+                MESSAGE.with(|message| message.borrow_mut().clear());
+            }
+            if let Some(key) = result {
+                match key {
+                    Char(c) if c.is_ascii_digit() && (c != '0' || !self.count.is_empty()) => {
+                        self.count.push(c);
+                        debug(&self.count);
+                        continue;
+                    },
+                    Char('G') | Char('g') | Char('\n') if !self.count.is_empty() => self.jump_to_count(),
+                    Char('q') | Key::Ctrl('c') => {
+                        self.quit();
+                        break;
+                    },
+                    Char('j') | Down if self.sidebar_focus => self.select_sidebar_channel(self.sidebar_i + 1),
+                    Char('k') | Up if self.sidebar_focus => self.select_sidebar_channel(self.sidebar_i.saturating_sub(1)),
+                    Char('g') if self.sidebar_focus => self.select_sidebar_channel(0),
+                    Char('G') if self.sidebar_focus => self.select_sidebar_channel(usize::MAX),
+                    Right if self.sidebar_focus => self.toggle_sidebar_focus(),
+                    key => match KEY_BINDINGS.iter().find(|binding| binding.0.contains(&key)) {
+                        Some((_, _, _, action)) => action(self),
+                        None => debug(&"key not supported (press h for help)".to_string()),
+                    },
                 }
-                _ => ()
             }
-            self.i = self.i % self.n;
+            if pressed {
+                self.count.clear();
+            }
+            self.i %= self.n;
         };
     }
 }
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    let safe_mode = args.iter().any(|arg| arg == "--safe-mode");
+    let presentation = args.iter().any(|arg| arg == "--presentation");
+    let inline = args.iter().any(|arg| arg == "--inline");
+    let no_color = args.iter().any(|arg| arg == "--no-color");
+    let mix = args.iter().position(|arg| arg == "--mix").and_then(|i| args.get(i + 1)).cloned();
+    if let Some(i) = args.iter().position(|arg| arg == "--mix") {
+        args.drain(i..min(i + 2, args.len()));
+    }
+    args.retain(|arg| arg != "--safe-mode" && arg != "--presentation" && arg != "--inline" && arg != "--no-color");
+    let mut app_config = if safe_mode {
+        AppConfig { read_only: true, safe_mode: true, restricted_profile: configured_restricted_profile(), ..Default::default() }
+    } else {
+        AppConfig { read_only: presentation, presentation, ..load_config() }
+    };
+    app_config.inline = app_config.inline || inline;
+    if no_color || app_config.no_color {
+        env::set_var("NO_COLOR", "1");
+    }
+    let group = mix.or(app_config.startup_mix.clone()).filter(|name| app_config.channel_tags.contains_key(name));
     let mut yts = YoutubeSubscribtions{
             n: 0,
             start: 0,
             filter: "".to_string(),
             i: 0,
             toshow: vec![],
-            videos: Videos{videos: vec![]},
-            app_config: load_config(),
+            total_videos: 0,
+            unread: 0,
+            pinned: 0,
+            marked: vec![],
+            videos: Videos::default(),
+            state: load_state(&app_config),
+            subscriptions_modified: (None, None),
+            translate_titles: false,
+            translations: HashMap::new(),
+            theme: get_theme(&app_config),
+            saved_view: None,
+            last_search: None,
+            count: "".to_string(),
+            previewed: None,
+            marks: HashMap::new(),
+            previous_position: None,
+            listed_urls: vec![],
+            sidebar_focus: false,
+            sidebar_i: 0,
+            group,
+            continuations: HashMap::new(),
+            refresh: None,
+            durations: None,
+            command_unlocked: false,
+            subscription: None,
+            statistics: None,
+            pending: vec![],
+            pending_new: vec![],
+            new_urls: HashSet::new(),
+            last_refresh: Instant::now(),
+            app_config,
     };
+    if args.len() == 3 && ["add", "import", "mark-watched", "unmark", "import-archive"].contains(&args[1].as_str()) {
+        require_pin(&yts.app_config);
+    }
     match args.len() {
+        2 if args[1] == "login" => runtime().block_on(oauth_login(&yts.app_config)),
+        3 if args[1] == "add" => preview_and_add_channel(&args[2], &yts.app_config),
+        3 if args[1] == "cache" => cache_command(&args[2], &yts.app_config),
+        2 if args[1] == "refresh" => refresh_command(&yts.app_config),
+        3 if args[1] == "import" => import_opml(&args[2], &yts.app_config),
+        3 if args[1] == "mark-watched" || args[1] == "unmark" => {
+            let videos = load(false, false, &no_progress, &yts.app_config).unwrap();
+            let count = set_watched_matching(&mut yts.state, &videos.videos, &args[2], args[1] == "mark-watched");
+            save_state(&mut yts.state, &yts.app_config);
+            println!("{} videos updated", count);
+        },
+        3 if args[1] == "import-archive" => import_archive(&args[2], &yts.app_config),
         2 => {
             match args[1].parse::<usize>() {
                 Ok(_n) => yts.download(_n),
@@ -723,3 +5172,281 @@ fn main() {
         _ => yts.run(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(watched: &[&str]) -> State {
+        State { watched: watched.iter().map(|id| id.to_string()).collect(), ..Default::default() }
+    }
+
+    #[test]
+    fn merge_state_keeps_the_changes_of_both_instances() {
+        let mut ours = state(&["a", "b"]);
+        ours.saved = Some(Box::new(ours.clone()));
+        ours.watched.insert("c".to_string());
+        ours.watched.remove("a");
+        ours.watch_time.insert("2020-01-01".to_string(), 60);
+        let mut on_disk = state(&["a", "b", "d"]);
+        on_disk.watch_time.insert("2020-01-01".to_string(), 30);
+        let merged = merge_state(&ours, on_disk);
+        assert_eq!(merged.watched, ["b", "c", "d"].iter().map(|id| id.to_string()).collect());
+        assert_eq!(merged.watch_time["2020-01-01"], 90);
+    }
+
+    #[test]
+    fn merge_state_keeps_unmutes_and_new_history() {
+        let mut ours = State { muted: vec![MutedChannel { channel: "x".to_string(), until: None }], ..Default::default() };
+        ours.saved = Some(Box::new(ours.clone()));
+        ours.muted.clear();
+        ours.history.insert("/".to_string(), vec!["mine".to_string()]);
+        let on_disk = State {
+            muted: vec![MutedChannel { channel: "x".to_string(), until: None }, MutedChannel { channel: "y".to_string(), until: None }],
+            history: vec![("/".to_string(), vec!["mine".to_string(), "theirs".to_string()])].into_iter().collect(),
+            ..Default::default()
+        };
+        let merged = merge_state(&ours, on_disk);
+        assert_eq!(merged.muted, vec![MutedChannel { channel: "y".to_string(), until: None }]);
+        assert_eq!(merged.history["/"], vec!["theirs".to_string(), "mine".to_string()]);
+    }
+
+    #[test]
+    fn migrate_applies_the_missing_migrations() {
+        let mut json = serde_json::json!({"version": 1, "videos": [{"url": "https://www.youtube.com/shorts/a"}]});
+        assert!(migrate(&mut json, CACHE_MIGRATIONS));
+        assert_eq!(json["version"], serde_json::json!(CACHE_MIGRATIONS.len()));
+        assert_eq!(json["videos"][0]["feed"], serde_json::json!(""));
+        assert_eq!(json["videos"][0]["short"], serde_json::json!(true));
+        assert_eq!(json["fetched"], serde_json::json!({}));
+    }
+
+    #[test]
+    fn migrate_reports_unchanged_content() {
+        let mut json = serde_json::json!({"watched": []});
+        assert!(!migrate(&mut json, &STATE_MIGRATIONS[..1]));
+        assert_eq!(json["version"], serde_json::json!(1));
+        let mut current = serde_json::json!({"version": CONFIG_MIGRATIONS.len()});
+        assert!(!migrate(&mut current, CONFIG_MIGRATIONS));
+        assert!(!migrate(&mut serde_json::json!([]), STATE_MIGRATIONS));
+    }
+
+    #[test]
+    fn format_timestamp_formats_utc_dates() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00+00:00");
+        assert_eq!(format_timestamp(951825600), "2000-02-29T12:00:00+00:00");
+        assert_eq!(format_timestamp(-1), "1969-12-31T23:59:59+00:00");
+    }
+
+    #[test]
+    fn parse_timestamp_applies_the_offset() {
+        assert_eq!(parse_timestamp("2019-06-01T10:20:30+00:00"), Some(1559384430));
+        assert_eq!(parse_timestamp("2019-06-01T12:20:30+02:00"), Some(1559384430));
+        assert_eq!(parse_timestamp("2019-06-01T10:20:30.123-01:30"), Some(1559384430 + 5400));
+        assert_eq!(parse_timestamp("2019-06-01"), Some(1559347200));
+        assert_eq!(parse_timestamp("yesterday"), None);
+    }
+
+    #[test]
+    fn parse_timestamp_reads_format_timestamp() {
+        for timestamp in [0, 951825600, 1559384430] {
+            assert_eq!(parse_timestamp(&format_timestamp(timestamp)), Some(timestamp));
+        }
+    }
+
+    #[test]
+    fn parse_rfc2822_date_applies_the_zone() {
+        assert_eq!(parse_rfc2822_date("Tue, 07 Dec 2021 10:00:00 GMT").as_deref(), Some("2021-12-07T10:00:00+00:00"));
+        assert_eq!(parse_rfc2822_date("Tue, 07 Dec 2021 10:00:00 +0100").as_deref(), Some("2021-12-07T09:00:00+00:00"));
+        assert_eq!(parse_rfc2822_date("Mon, 06 Dec 2021 22:30 EST").as_deref(), Some("2021-12-07T03:30:00+00:00"));
+        assert_eq!(parse_rfc2822_date("7 Dec 21 10:00:00 -0030").as_deref(), Some("2021-12-07T10:30:00+00:00"));
+    }
+
+    #[test]
+    fn parse_rfc2822_date_without_weekday() {
+        assert_eq!(parse_rfc2822_date("07 Dec 2021 10:00:00 GMT").as_deref(), Some("2021-12-07T10:00:00+00:00"));
+        assert_eq!(parse_rfc2822_date("07 dec 2021 10:00:00").as_deref(), Some("2021-12-07T10:00:00+00:00"));
+    }
+
+    #[test]
+    fn parse_rfc2822_date_rejects_invalid_dates() {
+        assert_eq!(parse_rfc2822_date("Tue, 07 Foo 2021 10:00:00 GMT"), None);
+        assert_eq!(parse_rfc2822_date("Tue, 07 Dec 2021"), None);
+        assert_eq!(parse_rfc2822_date("Tue, 07 Dec 2021 ten GMT"), None);
+        assert_eq!(parse_rfc2822_date(""), None);
+    }
+
+    #[test]
+    fn parse_duration_reads_hours_minutes_and_seconds() {
+        assert_eq!(parse_duration("1:02:03"), Some(3723));
+        assert_eq!(parse_duration("02:03"), Some(123));
+        assert_eq!(parse_duration(" 123\n"), Some(123));
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("1:xx"), None);
+    }
+
+    fn video(duration: Option<u64>) -> Video {
+        serde_json::from_value(serde_json::json!({
+            "channel": "", "title": "", "thumbnail": "", "url": "", "published": "", "description": "", "duration": duration,
+        })).unwrap()
+    }
+
+    #[test]
+    fn matches_duration_compares_with_the_limit() {
+        assert_eq!(matches_duration(&video(Some(1500)), ">20m"), Some(true));
+        assert_eq!(matches_duration(&video(Some(1500)), "<20m"), Some(false));
+        assert_eq!(matches_duration(&video(Some(1500)), "<1h"), Some(true));
+        assert_eq!(matches_duration(&video(Some(60)), "<90s"), Some(true));
+        assert_eq!(matches_duration(&video(Some(60)), ">2"), Some(false));
+        assert_eq!(matches_duration(&video(None), "<5m"), Some(false));
+    }
+
+    #[test]
+    fn matches_duration_ignores_other_filters() {
+        assert_eq!(matches_duration(&video(Some(60)), "rust"), None);
+        assert_eq!(matches_duration(&video(Some(60)), ">abc"), None);
+        assert_eq!(matches_duration(&video(Some(60)), "<"), None);
+    }
+
+    #[test]
+    fn decode_entities_decodes_named_and_numeric_entities() {
+        assert_eq!(decode_entities("Tom &amp; Jerry &lt;3 &quot;hi&quot;"), "Tom & Jerry <3 \"hi\"");
+        assert_eq!(decode_entities("it&#39;s &#x27;ok&#X27;"), "it's 'ok'");
+    }
+
+    #[test]
+    fn decode_entities_keeps_unknown_entities() {
+        assert_eq!(decode_entities("a & b"), "a & b");
+        assert_eq!(decode_entities("&unknown; &amp"), "&unknown; &amp");
+        assert_eq!(decode_entities("&#xD800;"), "&#xD800;");
+    }
+
+    #[test]
+    fn wrap_breaks_between_words() {
+        assert_eq!(wrap("the quick brown fox", 10), vec!["the quick", "brown fox"]);
+        assert_eq!(wrap("one\n\ntwo", 10), vec!["one", "", "two"]);
+        assert_eq!(wrap("", 10), Vec::<String>::new());
+    }
+
+    #[test]
+    fn wrap_splits_words_longer_than_the_width() {
+        assert_eq!(wrap("abcdefgh ij", 3), vec!["abc", "def", "gh", "ij"]);
+        assert_eq!(wrap("éèà", 0), vec!["é", "è", "à"]);
+    }
+
+    #[test]
+    fn find_url_stops_before_trailing_punctuation() {
+        let text = "see https://example.com/a?b=1, or (http://x.org).";
+        let (start, end) = find_url(text).unwrap();
+        assert_eq!(&text[start..end], "https://example.com/a?b=1");
+        let rest = &text[end..];
+        assert_eq!(find_url(rest).map(|(start, end)| &rest[start..end]), Some("http://x.org"));
+        assert_eq!(extract_urls(text), vec!["https://example.com/a?b=1", "http://x.org"]);
+    }
+
+    #[test]
+    fn find_url_returns_the_first_url() {
+        let text = "<a href=\"http://b.org\">https://a.org</a>";
+        assert_eq!(find_url(text).map(|(start, end)| &text[start..end]), Some("http://b.org"));
+        assert_eq!(find_url("no links here"), None);
+    }
+
+    fn published(channel: &str, title: &str, date: &str) -> Video {
+        serde_json::from_value(serde_json::json!({
+            "channel": channel, "title": title, "thumbnail": "", "url": "", "published": date, "description": "",
+        })).unwrap()
+    }
+
+    #[test]
+    fn tokenize_keeps_quoted_spaces() {
+        assert_eq!(tokenize(r#"channel:"Some Channel"  rust"#), vec!["channel:Some Channel", "rust"]);
+        assert_eq!(tokenize(r#""a b" "c"#), vec!["a b", "c"]);
+        assert_eq!(tokenize("  "), Vec::<String>::new());
+    }
+
+    #[test]
+    fn matches_expression_combines_keys_and_keywords() {
+        let video = published("Some Channel", "Learning rust", "2020-01-15T10:00:00+00:00");
+        assert!(matches_expression(&video, r#"channel:"Some Channel" rust"#));
+        assert!(matches_expression(&video, r#"filter:channel:"Some" Learning"#));
+        assert!(!matches_expression(&video, r#"channel:"Other Channel" rust"#));
+        assert!(!matches_expression(&video, "rust go"));
+    }
+
+    #[test]
+    fn matches_expression_treats_unknown_keys_as_keywords() {
+        assert!(matches_expression(&published("c", "tags:rust", ""), "tags:rust"));
+        assert!(!matches_expression(&published("c", "rust", ""), "tags:rust"));
+    }
+
+    #[test]
+    fn matches_expression_date_bounds() {
+        let video = published("c", "t", "2020-01-15T10:00:00+00:00");
+        assert!(matches_expression(&video, "after:2020-01-15 before:2020-01-16"));
+        assert!(matches_expression(&video, "after:2020-01-01 before:2020-02-01"));
+        assert!(!matches_expression(&video, "after:2020-01-16"));
+        assert!(!matches_expression(&video, "before:2020-01-15"));
+    }
+
+    #[test]
+    fn filter_videos_negates_channel_filters() {
+        let videos = vec![published("Rust", "a", ""), published("rust", "b", ""), published("Go", "c", "")];
+        let titles = |videos: Vec<Video>| videos.into_iter().map(|video| video.title).collect::<Vec<String>>();
+        assert_eq!(titles(filter_videos(&videos, "!channel:rust", false)), vec!["c"]);
+        assert_eq!(titles(filter_videos(&videos, "-channel:rust", true)), vec!["a", "c"]);
+        assert_eq!(titles(filter_videos(&videos, "channel:RUST", false)), vec!["a", "b"]);
+        assert!(matches_expression(&videos[0], "channel:Rust !b"));
+        assert!(!matches_expression(&videos[1], "channel:rust !b"));
+    }
+
+    #[test]
+    fn filter_videos_invalid_regex_matches_nothing() {
+        let videos = vec![published("c", "(a", ""), published("c", "b", "")];
+        assert!(filter_videos(&videos, "re:(", false).is_empty());
+        assert_eq!(filter_videos(&videos, "!re:(", false).len(), 2);
+        assert_eq!(filter_videos(&videos, "re:^B$", false).len(), 1);
+        assert!(!matches_expression(&videos[0], "re:("));
+    }
+
+    fn listed(url: &str, feed: &str, added: Option<i64>) -> Video {
+        serde_json::from_value(serde_json::json!({
+            "channel": "c", "title": "t", "thumbnail": "", "url": url, "published": "", "description": "", "feed": feed, "added": added,
+        })).unwrap()
+    }
+
+    #[test]
+    fn merge_fetched_keeps_videos_listed_by_two_feeds_in_place() {
+        let app_config = AppConfig { ..Default::default() };
+        let x = "https://www.youtube.com/v/xxxxxxxxxxx?version=3";
+        // a copy from another feed, as merged from the cache of another instance
+        let mut videos = Videos { videos: vec![listed(x, "channel", Some(1)), listed(x, "playlist", Some(5))], ..Default::default() };
+        videos.fetched.insert("channel".to_string(), 1);
+        for feed in ["channel", "playlist", "channel", "playlist"] {
+            let fresh = vec![listed(x, feed, None), listed(&format!("https://www.youtube.com/v/{}", feed), feed, None)];
+            let new = merge_fetched(&mut videos, feed.to_string(), Fetched::Videos(fresh, Validator::default()), &app_config);
+            assert!(new.iter().all(|video| video.url != x));
+            let cached = videos.videos.iter().filter(|video| video.url == x).collect::<Vec<&Video>>();
+            assert_eq!(cached.len(), 1);
+            assert_eq!((cached[0].feed.as_str(), cached[0].added), ("channel", Some(1)));
+        }
+        assert_eq!(videos.videos.len(), 3);
+    }
+
+    #[test]
+    fn dedup_videos_keeps_the_first_of_each_id() {
+        let mut videos = vec![
+            listed("https://www.youtube.com/v/xxxxxxxxxxx?version=3", "a", Some(1)),
+            listed("https://www.youtube.com/shorts/xxxxxxxxxxx", "b", Some(2)),
+            listed("https://example.com/video", "c", Some(3)),
+        ];
+        dedup_videos(&mut videos);
+        assert_eq!(videos.iter().map(|video| video.feed.as_str()).collect::<Vec<&str>>(), vec!["a", "c"]);
+    }
+
+    #[test]
+    fn fnv1a_is_stable() {
+        assert_eq!(fnv1a(""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a("a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a("foobar"), 0x85944171f73967e8);
+    }
+}